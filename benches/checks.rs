@@ -0,0 +1,80 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use geo::point;
+use vatsim_open_data::{Airport, OpenData, Position, StationType, FIR};
+
+const STATION_TYPES: [StationType; 4] = [
+    StationType::Tower,
+    StationType::Ground,
+    StationType::Approach,
+    StationType::Center,
+];
+
+/// Deterministic linear congruential generator, so every run benchmarks the same data
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u32 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        u32::try_from(self.0 >> 40).unwrap()
+    }
+
+    fn designator(&mut self, len: usize) -> String {
+        (0..len)
+            .map(|_| char::from(b'A' + u8::try_from(self.next() % 26).unwrap()))
+            .collect()
+    }
+}
+
+/// Roughly the size of the world: 300 FIRs with 60 positions and 40 airports each. Prefixes and
+/// frequencies are drawn from realistic ranges, so collisions are as rare as in real data.
+fn world() -> OpenData {
+    let mut rng = Rng(1);
+    let mut open_data = OpenData::default();
+    for fir_idx in 0..300 {
+        let mut fir = FIR::default();
+        for pos_idx in 0..60 {
+            let prefix = rng.designator(4);
+            let frequency = 118_000_000 + (rng.next() % 2_720) * 5_000;
+            fir.positions.insert(
+                format!("POS{pos_idx}"),
+                Position {
+                    frequency,
+                    prefix,
+                    station_type: STATION_TYPES[pos_idx % STATION_TYPES.len()].clone(),
+                    name: None,
+                    radio_callsign: String::new(),
+                    cpdlc_logon: None,
+                    airspace_groups: vec![],
+                    gcap_tier: None,
+                },
+            );
+        }
+        for _ in 0..40 {
+            let airport = Airport {
+                name: String::new(),
+                iata_designator: Some(rng.designator(3)),
+                fallback_prefixes: vec![rng.designator(4)],
+                location: point!(x: 0.0, y: 0.0),
+                elevation: None,
+                position_priority: vec![],
+                runways: vec![],
+            };
+            fir.airports.insert(rng.designator(4), airport);
+        }
+        open_data.firs.insert(format!("F{fir_idx:03}"), fir);
+    }
+    open_data
+}
+
+fn checks(c: &mut Criterion) {
+    let open_data = world();
+    c.bench_function("run_checks world", |b| {
+        b.iter(|| open_data.run_checks());
+    });
+}
+
+criterion_group!(benches, checks);
+criterion_main!(benches);
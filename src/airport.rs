@@ -1,7 +1,10 @@
-use std::{collections::HashMap, fs::read_to_string, path::Path};
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
 
-use geo::Point;
+use geo::{Bearing, Distance, Geodesic, Point};
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::position::PositionReference;
 
@@ -15,17 +18,252 @@ pub struct Airport {
     pub elevation: Option<i32>,
     pub position_priority: Vec<Vec<PositionReference>>,
     #[serde(default)]
-    pub runways: Vec<String>,
+    pub runways: Vec<Runway>,
 }
 
 impl Airport {
-    pub fn from_toml(path: &Path) -> Result<HashMap<String, Self>, super::Error> {
-        Ok(toml::from_str(&read_to_string(path)?)?)
+    #[cfg(feature = "fs")]
+    pub fn from_toml(path: &Path) -> Result<BTreeMap<String, Self>, super::Error> {
+        Self::from_toml_str(&read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<BTreeMap<String, Self>, super::Error> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// The runway with an end designated `designator`
+    #[must_use]
+    pub fn runway(&self, designator: &str) -> Option<&Runway> {
+        self.runways.iter().find(|runway| {
+            runway.designator == designator || runway.reciprocal.as_deref() == Some(designator)
+        })
+    }
+
+    /// Invalid designators and runways whose ends are not reciprocal, see [`Runway::check`]
+    pub fn check_runways(&self) -> Result<(), Vec<RunwayError>> {
+        let errors = self
+            .runways
+            .iter()
+            .filter_map(|runway| runway.check().err())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// A runway, given in airport files either as a plain designator like `"08L"` or as table with
+/// both ends and their geometry
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(from = "RunwayDefinition", into = "RunwayDefinition")]
+pub struct Runway {
+    /// Designator of the end the heading and `threshold` refer to, e.g. `08L`
+    pub designator: String,
+    /// Designator of the opposite end, e.g. `26R`
+    pub reciprocal: Option<String>,
+    pub threshold: Option<Point>,
+    pub reciprocal_threshold: Option<Point>,
+    /// True heading in degrees, derived from the thresholds if not given
+    pub heading: Option<f64>,
+    /// In metres, derived from the thresholds if not given
+    pub length: Option<f64>,
+    /// Threshold elevation in feet
+    pub elevation: Option<i32>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum RunwayDefinition {
+    Designator(String),
+    Table(RunwayTable),
+}
+
+/// Unknown keys are rejected so that misspelt optional fields do not go unnoticed
+#[derive(Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RunwayTable {
+    designator: String,
+    reciprocal: Option<String>,
+    threshold: Option<Point>,
+    reciprocal_threshold: Option<Point>,
+    heading: Option<f64>,
+    length: Option<f64>,
+    elevation: Option<i32>,
+}
+
+impl From<RunwayDefinition> for Runway {
+    fn from(definition: RunwayDefinition) -> Self {
+        match definition {
+            RunwayDefinition::Designator(designator) => Self {
+                designator,
+                ..Default::default()
+            },
+            RunwayDefinition::Table(RunwayTable {
+                designator,
+                reciprocal,
+                threshold,
+                reciprocal_threshold,
+                heading,
+                length,
+                elevation,
+            }) => Self {
+                designator,
+                reciprocal,
+                threshold,
+                reciprocal_threshold,
+                heading,
+                length,
+                elevation,
+            },
+        }
+    }
+}
+
+impl From<Runway> for RunwayDefinition {
+    fn from(runway: Runway) -> Self {
+        // plain designators stay plain when writing files back
+        if runway
+            == (Runway {
+                designator: runway.designator.clone(),
+                ..Default::default()
+            })
+        {
+            return Self::Designator(runway.designator);
+        }
+        Self::Table(RunwayTable {
+            designator: runway.designator,
+            reciprocal: runway.reciprocal,
+            threshold: runway.threshold,
+            reciprocal_threshold: runway.reciprocal_threshold,
+            heading: runway.heading,
+            length: runway.length,
+            elevation: runway.elevation,
+        })
     }
 }
 
+#[derive(Debug, Error)]
+pub enum RunwayError {
+    #[error("invalid runway designator {0}, expected 01 to 36 optionally followed by L, C or R")]
+    InvalidDesignator(String),
+    #[error("runway ends {0} and {1} are not reciprocal")]
+    NotReciprocal(String, String),
+}
+
+impl Runway {
+    /// Heading given or from the threshold to the reciprocal threshold
+    #[must_use]
+    pub fn true_heading(&self) -> Option<f64> {
+        self.heading.or_else(|| {
+            let bearing = Geodesic::bearing(self.threshold?, self.reciprocal_threshold?);
+            Some(bearing.rem_euclid(360.0))
+        })
+    }
+
+    /// Length given or between the thresholds
+    #[must_use]
+    pub fn length(&self) -> Option<f64> {
+        self.length.or_else(|| {
+            Some(Geodesic::distance(
+                self.threshold?,
+                self.reciprocal_threshold?,
+            ))
+        })
+    }
+
+    /// Checks that the designators are valid and the reciprocal differs by 18 with the left and
+    /// right suffixes swapped.
+    pub fn check(&self) -> Result<(), RunwayError> {
+        let (number, side) = parse_designator(&self.designator)
+            .ok_or_else(|| RunwayError::InvalidDesignator(self.designator.clone()))?;
+        let Some(reciprocal) = &self.reciprocal else {
+            return Ok(());
+        };
+        let (reciprocal_number, reciprocal_side) = parse_designator(reciprocal)
+            .ok_or_else(|| RunwayError::InvalidDesignator(reciprocal.clone()))?;
+        let opposite_side = match side {
+            Some('L') => Some('R'),
+            Some('R') => Some('L'),
+            side => side,
+        };
+        if (number + 18 - 1) % 36 + 1 != reciprocal_number || opposite_side != reciprocal_side {
+            return Err(RunwayError::NotReciprocal(
+                self.designator.clone(),
+                reciprocal.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Number and side of designators like `08L`
+fn parse_designator(designator: &str) -> Option<(u8, Option<char>)> {
+    let (number, side) = match designator.len() {
+        2 => (designator, None),
+        3 => (designator.get(..2)?, designator.chars().nth(2)),
+        _ => return None,
+    };
+    let number = number
+        .parse()
+        .ok()
+        .filter(|n| (1..=36).contains(n) && number.bytes().all(|b| b.is_ascii_digit()))?;
+    match side {
+        None | Some('L' | 'C' | 'R') => Some((number, side)),
+        _ => None,
+    }
+}
+
+/// A runway end of an airport, see [`Airport::runway`]
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RunwayReference {
     pub icao: String,
     pub designator: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Airport, RunwayError};
+
+    #[test]
+    fn test_runways() {
+        let airports = Airport::from_toml_str(
+            r#"
+            [EDDM]
+            name = "München"
+            location = { x = 11.786, y = 48.354 }
+            position_priority = []
+            runways = [
+                "08L",
+                { designator = "08R", reciprocal = "26L", elevation = 1_467 },
+                { designator = "09", reciprocal = "26" },
+                { designator = "37C" },
+            ]
+            "#,
+        )
+        .unwrap();
+        let eddm = &airports["EDDM"];
+        assert_eq!(eddm.runway("26L").unwrap().elevation, Some(1_467));
+        assert_eq!(eddm.runway("08L").unwrap().reciprocal, None);
+
+        let errors = eddm.check_runways().unwrap_err();
+        assert!(matches!(&errors[0], RunwayError::NotReciprocal(a, b) if a == "09" && b == "26"));
+        assert!(matches!(&errors[1], RunwayError::InvalidDesignator(d) if d == "37C"));
+
+        // plain designators are written back unchanged
+        let toml = toml::to_string(&airports).unwrap();
+        assert!(toml.contains(r#"runways = ["08L", { designator = "08R""#));
+
+        let misspelt = Airport::from_toml_str(
+            r#"
+            [EDDM]
+            name = "München"
+            location = { x = 11.786, y = 48.354 }
+            position_priority = []
+            runways = [{ designator = "08R", reciprical = "26L" }]
+            "#,
+        );
+        assert!(misspelt.is_err());
+    }
+}
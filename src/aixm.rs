@@ -0,0 +1,627 @@
+//! Import of AIXM 5.1 `Airspace` features into [`Volume`]s.
+//!
+//! Supported are airspaces whose geometry components are plain `BASE`/`UNION` volumes with
+//! polygon patches made of point lists, arcs, circles and references to `GeoBorder` curves.
+//! Everything else is reported as unconverted, so it can be added by hand.
+
+use std::{collections::BTreeMap, io};
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
+
+use geo::{Coord, LineString, Point, Polygon};
+use roxmltree::{Document, Node};
+use thiserror::Error;
+
+use crate::{
+    geometry::{self, METERS_PER_FOOT, METERS_PER_NAUTICAL_MILE},
+    volume, Volume,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read file: {0}")]
+    FileRead(#[from] io::Error),
+    #[error("failed to parse xml: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("invalid arc resolution {0}, must be a positive number of degrees")]
+    InvalidResolution(f64),
+}
+
+#[derive(Debug, Error)]
+pub enum UnconvertedReason {
+    #[error("no airspace time slice")]
+    NoBaselineTimeSlice,
+    #[error("no designator, name or identifier")]
+    MissingDesignator,
+    #[error("no geometry component")]
+    MissingGeometry,
+    #[error("unsupported geometry operation {0}")]
+    UnsupportedOperation(String),
+    #[error("volume derived from contributor airspaces")]
+    Derived,
+    #[error("missing {0}")]
+    MissingLimit(&'static str),
+    #[error("unsupported vertical limit {0:?} ({1:?})")]
+    UnsupportedLimit(String, Option<String>),
+    #[error("missing horizontal projection")]
+    MissingHorizontalProjection,
+    #[error("{0} polygon patches, expected one")]
+    PatchCount(usize),
+    #[error("unsupported geometry element {0}")]
+    UnsupportedElement(String),
+    #[error("invalid coordinates {0:?}")]
+    InvalidCoordinates(String),
+    #[error("invalid arc in {0}")]
+    InvalidArc(String),
+    #[error("unresolved reference {0}")]
+    UnresolvedReference(String),
+    #[error("ring with less than three points")]
+    TooFewPoints,
+}
+
+#[derive(Debug)]
+pub struct Unconverted {
+    /// Designator, name or identifier of the airspace, its `gml:id` if it has none
+    pub id: String,
+    pub reason: UnconvertedReason,
+}
+
+#[derive(Debug, Default)]
+pub struct Import {
+    pub volumes: BTreeMap<String, Volume>,
+    pub unconverted: Vec<Unconverted>,
+}
+
+/// Reads an AIXM 5.1 file, see [`import_str`].
+#[cfg(feature = "fs")]
+pub fn import(path: &Path, resolution: f64) -> Result<Import, Error> {
+    import_str(&read_to_string(path)?, resolution)
+}
+
+/// Converts all `Airspace` features to volumes keyed by designator (or name), approximating arcs
+/// and circles with a point at least every `resolution` degrees.
+pub fn import_str(input: &str, resolution: f64) -> Result<Import, Error> {
+    if !geometry::valid_resolution(resolution) {
+        return Err(Error::InvalidResolution(resolution));
+    }
+    let doc = Document::parse(input)?;
+    let converter = Converter {
+        curves: curve_index(&doc),
+        resolution,
+    };
+
+    let mut import = Import::default();
+    for airspace in doc
+        .descendants()
+        .filter(|node| node.has_tag_name_local("Airspace"))
+    {
+        let time_slice = baseline_time_slice(airspace);
+        let id = time_slice
+            .and_then(|time_slice| {
+                child_text(time_slice, "designator").or_else(|| child_text(time_slice, "name"))
+            })
+            .or_else(|| child_text(airspace, "identifier"));
+        let (Some(time_slice), Some(id)) = (time_slice, id) else {
+            import.unconverted.push(Unconverted {
+                id: id
+                    .or_else(|| airspace.attribute_local("id"))
+                    .unwrap_or_default()
+                    .to_string(),
+                reason: if time_slice.is_none() {
+                    UnconvertedReason::NoBaselineTimeSlice
+                } else {
+                    UnconvertedReason::MissingDesignator
+                },
+            });
+            continue;
+        };
+        let id = id.to_string();
+        match converter.airspace(time_slice) {
+            Ok(volumes) => {
+                for volume in volumes {
+                    volume::insert_unique(&mut import.volumes, &id, volume);
+                }
+            }
+            Err(reason) => import.unconverted.push(Unconverted { id, reason }),
+        }
+    }
+
+    Ok(import)
+}
+
+trait NodeExt<'a, 'input: 'a> {
+    fn has_tag_name_local(&self, name: &str) -> bool;
+    fn child_element(&self, name: &str) -> Option<Node<'a, 'input>>;
+    fn child_elements(&self, name: &str) -> impl Iterator<Item = Node<'a, 'input>>;
+    fn attribute_local(&self, name: &str) -> Option<&'a str>;
+}
+
+impl<'a, 'input: 'a> NodeExt<'a, 'input> for Node<'a, 'input> {
+    fn has_tag_name_local(&self, name: &str) -> bool {
+        self.is_element() && self.tag_name().name() == name
+    }
+
+    fn child_element(&self, name: &str) -> Option<Node<'a, 'input>> {
+        self.child_elements(name).next()
+    }
+
+    fn child_elements(&self, name: &str) -> impl Iterator<Item = Node<'a, 'input>> {
+        let name = name.to_string();
+        self.children()
+            .filter(move |child| child.has_tag_name_local(&name))
+    }
+
+    fn attribute_local(&self, name: &str) -> Option<&'a str> {
+        self.attributes()
+            .find(|attribute| attribute.name() == name)
+            .map(|attribute| attribute.value())
+    }
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.child_element(name)
+        .and_then(|child| child.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+fn baseline_time_slice<'a, 'input>(airspace: Node<'a, 'input>) -> Option<Node<'a, 'input>> {
+    let mut slices = airspace
+        .child_elements("timeSlice")
+        .filter_map(|time_slice| time_slice.child_element("AirspaceTimeSlice"))
+        .peekable();
+    let first = *slices.peek()?;
+    Some(
+        slices
+            .find(|slice| child_text(*slice, "interpretation") == Some("BASELINE"))
+            .unwrap_or(first),
+    )
+}
+
+/// Indexes all curves by `gml:id`, and the curves of `GeoBorder`s also by their identifier.
+fn curve_index<'a, 'input>(doc: &'a Document<'input>) -> BTreeMap<String, Node<'a, 'input>> {
+    let mut curves = BTreeMap::new();
+    for node in doc.descendants() {
+        if node.has_tag_name_local("Curve") {
+            if let Some(id) = node.attribute_local("id") {
+                curves.insert(id.to_string(), node);
+            }
+        } else if node.has_tag_name_local("GeoBorder") {
+            let curve = node
+                .descendants()
+                .find(|descendant| descendant.has_tag_name_local("Curve"));
+            if let Some(curve) = curve {
+                for id in [node.attribute_local("id"), child_text(node, "identifier")]
+                    .into_iter()
+                    .flatten()
+                {
+                    curves.insert(id.to_string(), curve);
+                }
+            }
+        }
+    }
+    curves
+}
+
+struct Converter<'a, 'input> {
+    curves: BTreeMap<String, Node<'a, 'input>>,
+    resolution: f64,
+}
+
+impl<'a, 'input> Converter<'a, 'input> {
+    fn airspace(&self, time_slice: Node<'a, 'input>) -> Result<Vec<Volume>, UnconvertedReason> {
+        let components = time_slice
+            .child_elements("geometryComponent")
+            .filter_map(|component| component.child_element("AirspaceGeometryComponent"))
+            .collect::<Vec<_>>();
+        if components.is_empty() {
+            return Err(UnconvertedReason::MissingGeometry);
+        }
+
+        components
+            .into_iter()
+            .map(|component| {
+                match child_text(component, "operation") {
+                    None | Some("BASE" | "UNION") => {}
+                    Some(operation) => {
+                        return Err(UnconvertedReason::UnsupportedOperation(
+                            operation.to_string(),
+                        ))
+                    }
+                }
+                let volume = component
+                    .child_element("theAirspaceVolume")
+                    .and_then(|volume| volume.child_element("AirspaceVolume"))
+                    .ok_or(UnconvertedReason::MissingGeometry)?;
+                self.volume(volume)
+            })
+            .collect()
+    }
+
+    fn volume(&self, volume: Node<'a, 'input>) -> Result<Volume, UnconvertedReason> {
+        if volume.child_element("contributorAirspace").is_some() {
+            return Err(UnconvertedReason::Derived);
+        }
+        let lower_level = limit(volume, "lowerLimit")?;
+        let upper_level = limit(volume, "upperLimit")?;
+
+        let patches = volume
+            .child_element("horizontalProjection")
+            .and_then(|projection| projection.child_element("Surface"))
+            .and_then(|surface| surface.child_element("patches"))
+            .ok_or(UnconvertedReason::MissingHorizontalProjection)?
+            .child_elements("PolygonPatch")
+            .collect::<Vec<_>>();
+        let [patch] = patches[..] else {
+            return Err(UnconvertedReason::PatchCount(patches.len()));
+        };
+
+        let exterior = patch
+            .child_element("exterior")
+            .ok_or(UnconvertedReason::MissingHorizontalProjection)?;
+        let interiors = patch
+            .child_elements("interior")
+            .map(|interior| self.ring(interior))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Volume::new(
+            lower_level,
+            upper_level,
+            Polygon::new(self.ring(exterior)?, interiors),
+        ))
+    }
+
+    fn ring(&self, boundary: Node<'a, 'input>) -> Result<LineString, UnconvertedReason> {
+        let mut coords = vec![];
+        for ring in boundary.children().filter(Node::is_element) {
+            match ring.tag_name().name() {
+                "LinearRing" => coords.extend(positions(ring)?),
+                "Ring" => {
+                    for member in ring.child_elements("curveMember") {
+                        let curve = match member.attribute_local("href") {
+                            Some(href) => {
+                                let id =
+                                    href.trim_start_matches('#').trim_start_matches("urn:uuid:");
+                                *self.curves.get(id).ok_or_else(|| {
+                                    UnconvertedReason::UnresolvedReference(href.to_string())
+                                })?
+                            }
+                            None => member
+                                .children()
+                                .find(Node::is_element)
+                                .ok_or(UnconvertedReason::MissingHorizontalProjection)?,
+                        };
+                        coords.extend(self.curve(curve)?);
+                    }
+                }
+                other => return Err(UnconvertedReason::UnsupportedElement(other.to_string())),
+            }
+        }
+
+        let mut ring = LineString::new(coords);
+        if ring.0.len() < 3 {
+            return Err(UnconvertedReason::TooFewPoints);
+        }
+        ring.close();
+        Ok(ring)
+    }
+
+    fn curve(&self, curve: Node<'a, 'input>) -> Result<Vec<Coord>, UnconvertedReason> {
+        match curve.tag_name().name() {
+            "LineString" => positions(curve),
+            "Curve" => {
+                let mut coords = vec![];
+                for segment in curve
+                    .child_element("segments")
+                    .into_iter()
+                    .flat_map(|segments| segments.children().filter(Node::is_element))
+                {
+                    coords.extend(self.segment(segment)?);
+                }
+                Ok(coords)
+            }
+            other => Err(UnconvertedReason::UnsupportedElement(other.to_string())),
+        }
+    }
+
+    fn segment(&self, segment: Node<'a, 'input>) -> Result<Vec<Coord>, UnconvertedReason> {
+        let name = segment.tag_name().name();
+        match name {
+            "GeodesicString" | "LineStringSegment" => positions(segment),
+            "ArcByCenterPoint" | "CircleByCenterPoint" => {
+                let invalid = || UnconvertedReason::InvalidArc(name.to_string());
+                let center = Point::from(*positions(segment)?.first().ok_or_else(invalid)?);
+                let radius_node = segment.child_element("radius").ok_or_else(invalid)?;
+                let radius = radius_node
+                    .text()
+                    .and_then(|radius| radius.trim().parse::<f64>().ok())
+                    .ok_or_else(invalid)?
+                    * match radius_node.attribute("uom") {
+                        Some("[nmi_i]" | "NM") => METERS_PER_NAUTICAL_MILE,
+                        Some("km" | "KM") => 1000.0,
+                        Some("[ft_i]" | "FT") => METERS_PER_FOOT,
+                        Some("[mi_i]") => 1609.344,
+                        Some("m" | "M") | None => 1.0,
+                        Some(_) => return Err(invalid()),
+                    };
+
+                if name == "CircleByCenterPoint" {
+                    return Ok(geometry::circle(center, radius, self.resolution).0);
+                }
+                let angle = |name| {
+                    segment
+                        .child_element(name)
+                        .and_then(|angle| angle.text())
+                        .and_then(|angle| angle.trim().parse::<f64>().ok())
+                        .ok_or_else(invalid)
+                };
+                let (start, end) = (angle("startAngle")?, angle("endAngle")?);
+                // AIXM angles are bearings, arcs run clockwise towards the larger angle
+                Ok(geometry::arc(
+                    center,
+                    radius,
+                    start,
+                    end,
+                    end > start,
+                    self.resolution,
+                ))
+            }
+            other => Err(UnconvertedReason::UnsupportedElement(other.to_string())),
+        }
+    }
+}
+
+/// Collects `posList` and `pos` coordinates below `node`, honouring the axis order of the
+/// nearest `srsName` (latitude first for the default `EPSG:4326`).
+fn positions(node: Node) -> Result<Vec<Coord>, UnconvertedReason> {
+    let lon_first = node
+        .ancestors()
+        .find_map(|ancestor| ancestor.attribute("srsName"))
+        .is_some_and(|srs| srs.contains("CRS84"));
+
+    let mut coords = vec![];
+    for list in node.descendants().filter(|descendant| {
+        descendant.has_tag_name_local("posList") || descendant.has_tag_name_local("pos")
+    }) {
+        let text = list.text().unwrap_or_default();
+        let values = text
+            .split_whitespace()
+            .map(str::parse::<f64>)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| UnconvertedReason::InvalidCoordinates(text.to_string()))?;
+        if values.len() % 2 != 0 {
+            return Err(UnconvertedReason::InvalidCoordinates(text.to_string()));
+        }
+        coords.extend(values.chunks_exact(2).map(|pair| {
+            if lon_first {
+                Coord {
+                    x: pair[0],
+                    y: pair[1],
+                }
+            } else {
+                Coord {
+                    x: pair[1],
+                    y: pair[0],
+                }
+            }
+        }));
+    }
+    Ok(coords)
+}
+
+/// Converts a vertical limit to a flight level, `AGL` heights are treated as `AMSL`.
+fn limit(volume: Node, name: &'static str) -> Result<u64, UnconvertedReason> {
+    let node = volume
+        .child_element(name)
+        .ok_or(UnconvertedReason::MissingLimit(name))?;
+    let value = node.text().unwrap_or_default().trim();
+    let uom = node.attribute("uom");
+    let unsupported =
+        || UnconvertedReason::UnsupportedLimit(value.to_string(), uom.map(ToString::to_string));
+
+    match value {
+        "GND" => return Ok(0),
+        "UNL" => return Ok(999),
+        _ => {}
+    }
+    let number = value.parse::<f64>().map_err(|_| unsupported())?;
+    let level = match uom {
+        Some("FL") => number,
+        Some("FT") => number / 100.0,
+        Some("M") => number / METERS_PER_FOOT / 100.0,
+        _ => return Err(unsupported()),
+    };
+    if level < 0.0 {
+        return Err(unsupported());
+    }
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        reason = "checked to be positive, flight levels are small"
+    )]
+    Ok(level.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::{Contains, Point};
+
+    use crate::DEFAULT_ARC_RESOLUTION;
+
+    const SAMPLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<message:AIXMBasicMessage xmlns:message="http://www.aixm.aero/schema/5.1/message"
+    xmlns:aixm="http://www.aixm.aero/schema/5.1" xmlns:gml="http://www.opengis.net/gml/3.2"
+    xmlns:xlink="http://www.w3.org/1999/xlink" gml:id="msg">
+  <message:hasMember>
+    <aixm:GeoBorder gml:id="border1">
+      <gml:identifier codeSpace="urn:uuid:">b0a1</gml:identifier>
+      <aixm:timeSlice>
+        <aixm:GeoBorderTimeSlice gml:id="border1ts">
+          <aixm:border>
+            <aixm:Curve gml:id="border1curve" srsName="urn:ogc:def:crs:EPSG::4326">
+              <gml:segments>
+                <gml:GeodesicString>
+                  <gml:posList>48.0 12.0 49.0 12.0</gml:posList>
+                </gml:GeodesicString>
+              </gml:segments>
+            </aixm:Curve>
+          </aixm:border>
+        </aixm:GeoBorderTimeSlice>
+      </aixm:timeSlice>
+    </aixm:GeoBorder>
+  </message:hasMember>
+  <message:hasMember>
+    <aixm:Airspace gml:id="as1">
+      <aixm:timeSlice>
+        <aixm:AirspaceTimeSlice gml:id="as1ts">
+          <aixm:interpretation>BASELINE</aixm:interpretation>
+          <aixm:designator>EDMMTEST</aixm:designator>
+          <aixm:geometryComponent>
+            <aixm:AirspaceGeometryComponent gml:id="as1gc">
+              <aixm:operation>BASE</aixm:operation>
+              <aixm:theAirspaceVolume>
+                <aixm:AirspaceVolume gml:id="as1v">
+                  <aixm:upperLimit uom="FL">245</aixm:upperLimit>
+                  <aixm:lowerLimit uom="FT">5500</aixm:lowerLimit>
+                  <aixm:horizontalProjection>
+                    <aixm:Surface gml:id="as1s" srsName="urn:ogc:def:crs:EPSG::4326">
+                      <gml:patches>
+                        <gml:PolygonPatch>
+                          <gml:exterior>
+                            <gml:Ring>
+                              <gml:curveMember>
+                                <gml:Curve gml:id="as1c">
+                                  <gml:segments>
+                                    <gml:GeodesicString>
+                                      <gml:posList>49.0 11.0 48.0 11.0</gml:posList>
+                                    </gml:GeodesicString>
+                                  </gml:segments>
+                                </gml:Curve>
+                              </gml:curveMember>
+                              <gml:curveMember xlink:href="urn:uuid:b0a1"/>
+                            </gml:Ring>
+                          </gml:exterior>
+                        </gml:PolygonPatch>
+                      </gml:patches>
+                    </aixm:Surface>
+                  </aixm:horizontalProjection>
+                </aixm:AirspaceVolume>
+              </aixm:theAirspaceVolume>
+            </aixm:AirspaceGeometryComponent>
+          </aixm:geometryComponent>
+        </aixm:AirspaceTimeSlice>
+      </aixm:timeSlice>
+    </aixm:Airspace>
+  </message:hasMember>
+  <message:hasMember>
+    <aixm:Airspace gml:id="as2">
+      <aixm:timeSlice>
+        <aixm:AirspaceTimeSlice gml:id="as2ts">
+          <aixm:designator>EDDMCTR</aixm:designator>
+          <aixm:geometryComponent>
+            <aixm:AirspaceGeometryComponent gml:id="as2gc">
+              <aixm:operation>BASE</aixm:operation>
+              <aixm:theAirspaceVolume>
+                <aixm:AirspaceVolume gml:id="as2v">
+                  <aixm:upperLimit uom="FT">4000</aixm:upperLimit>
+                  <aixm:lowerLimit uom="OTHER">GND</aixm:lowerLimit>
+                  <aixm:horizontalProjection>
+                    <aixm:Surface gml:id="as2s" srsName="urn:ogc:def:crs:EPSG::4326">
+                      <gml:patches>
+                        <gml:PolygonPatch>
+                          <gml:exterior>
+                            <gml:Ring>
+                              <gml:curveMember>
+                                <gml:Curve gml:id="as2c">
+                                  <gml:segments>
+                                    <gml:CircleByCenterPoint numArc="1">
+                                      <gml:pos>48.35 11.78</gml:pos>
+                                      <gml:radius uom="[nmi_i]">10</gml:radius>
+                                    </gml:CircleByCenterPoint>
+                                  </gml:segments>
+                                </gml:Curve>
+                              </gml:curveMember>
+                            </gml:Ring>
+                          </gml:exterior>
+                        </gml:PolygonPatch>
+                      </gml:patches>
+                    </aixm:Surface>
+                  </aixm:horizontalProjection>
+                </aixm:AirspaceVolume>
+              </aixm:theAirspaceVolume>
+            </aixm:AirspaceGeometryComponent>
+          </aixm:geometryComponent>
+          <aixm:geometryComponent>
+            <aixm:AirspaceGeometryComponent gml:id="as2gc2">
+              <aixm:operation>SUBTR</aixm:operation>
+            </aixm:AirspaceGeometryComponent>
+          </aixm:geometryComponent>
+        </aixm:AirspaceTimeSlice>
+      </aixm:timeSlice>
+    </aixm:Airspace>
+  </message:hasMember>
+</message:AIXMBasicMessage>
+"#;
+
+    #[test]
+    fn test_import() {
+        let import = super::import_str(SAMPLE, DEFAULT_ARC_RESOLUTION).unwrap();
+        assert!(matches!(
+            super::import_str(SAMPLE, 0.0),
+            Err(super::Error::InvalidResolution(_))
+        ));
+
+        assert_eq!(import.volumes.len(), 1);
+        let volume = &import.volumes["EDMMTEST"];
+        assert_eq!(volume.lower_level(), 55);
+        assert_eq!(volume.upper_level(), 245);
+        assert!(volume.lateral_bounds().contains(&Point::new(11.5, 48.5)));
+        assert!(!volume.lateral_bounds().contains(&Point::new(12.5, 48.5)));
+
+        assert_eq!(import.unconverted.len(), 1);
+        assert_eq!(import.unconverted[0].id, "EDDMCTR");
+        assert!(matches!(
+            &import.unconverted[0].reason,
+            super::UnconvertedReason::UnsupportedOperation(op) if op == "SUBTR"
+        ));
+    }
+
+    #[test]
+    fn test_unidentified() {
+        let import = super::import_str(
+            r#"<message:AIXMBasicMessage xmlns:message="http://www.aixm.aero/schema/5.1/message"
+    xmlns:aixm="http://www.aixm.aero/schema/5.1" xmlns:gml="http://www.opengis.net/gml/3.2">
+  <message:hasMember>
+    <aixm:Airspace gml:id="as1">
+      <gml:identifier codeSpace="urn:uuid:">a1</gml:identifier>
+    </aixm:Airspace>
+  </message:hasMember>
+  <message:hasMember>
+    <aixm:Airspace gml:id="as2">
+      <aixm:timeSlice>
+        <aixm:AirspaceTimeSlice gml:id="as2ts">
+          <aixm:interpretation>BASELINE</aixm:interpretation>
+        </aixm:AirspaceTimeSlice>
+      </aixm:timeSlice>
+    </aixm:Airspace>
+  </message:hasMember>
+</message:AIXMBasicMessage>
+"#,
+            DEFAULT_ARC_RESOLUTION,
+        )
+        .unwrap();
+
+        assert!(import.volumes.is_empty());
+        assert_eq!(import.unconverted.len(), 2);
+        assert_eq!(import.unconverted[0].id, "a1");
+        assert!(matches!(
+            import.unconverted[0].reason,
+            super::UnconvertedReason::NoBaselineTimeSlice
+        ));
+        assert_eq!(import.unconverted[1].id, "as2");
+        assert!(matches!(
+            import.unconverted[1].reason,
+            super::UnconvertedReason::MissingDesignator
+        ));
+    }
+}
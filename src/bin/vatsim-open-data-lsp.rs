@@ -0,0 +1,26 @@
+//! Language server for editing the data files, speaking LSP on stdin/stdout.
+
+use std::{io, process::ExitCode};
+
+use lsp_server::Connection;
+use tracing::error;
+use tracing_subscriber::EnvFilter;
+
+fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_env("VATSIM_OPEN_DATA_LOG"))
+        .with_writer(io::stderr)
+        .init();
+
+    let (connection, io_threads) = Connection::stdio();
+    let result = vatsim_open_data::lsp::serve(&connection);
+    drop(connection);
+    if let Err(e) = result
+        .map_err(|e| e.to_string())
+        .and_then(|()| io_threads.join().map_err(|e| e.to_string()))
+    {
+        error!("{e}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
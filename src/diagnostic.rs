@@ -0,0 +1,533 @@
+//! Structured validation results for machine consumption, e.g. in CI annotations.
+
+#[cfg(feature = "fs")]
+use std::fs::read_to_string;
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{vateud8, Error, InvalidPositionReferenceType};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A check, configurable by its id in `config.toml`
+#[derive(Clone, Copy, Debug)]
+pub struct Rule {
+    pub id: &'static str,
+    pub default_severity: Severity,
+    pub description: &'static str,
+}
+
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "read-error",
+        default_severity: Severity::Error,
+        description: "a data file could not be read or parsed",
+    },
+    Rule {
+        id: "invalid-volume",
+        default_severity: Severity::Error,
+        description: "volume levels are inconsistent",
+    },
+    Rule {
+        id: "invalid-runway",
+        default_severity: Severity::Error,
+        description: "a runway designator is invalid or its ends are not reciprocal",
+    },
+    Rule {
+        id: "duplicate-position",
+        default_severity: Severity::Error,
+        description: "positions share frequency, station type and an overlapping prefix",
+    },
+    Rule {
+        id: "duplicate-airport",
+        default_severity: Severity::Error,
+        description: "airports share an ICAO, IATA or fallback designator",
+    },
+    Rule {
+        id: "invalid-position-reference",
+        default_severity: Severity::Error,
+        description: "a sector or airport references a position that does not exist",
+    },
+    Rule {
+        id: "non-neighbour-reference",
+        default_severity: Severity::Warning,
+        description: "a sector or airport references a position of a FIR not declared as neighbour",
+    },
+    Rule {
+        id: "unknown-uir",
+        default_severity: Severity::Warning,
+        description: "a FIR belongs to a UIR without data folder, likely a misspelling",
+    },
+    Rule {
+        id: "unknown-neighbour",
+        default_severity: Severity::Error,
+        description: "a FIR declares a neighbour that does not exist",
+    },
+    Rule {
+        id: "vateud8-fetch",
+        default_severity: Severity::Warning,
+        description: "the VATEUD8 position list could not be retrieved, its checks are skipped",
+    },
+    Rule {
+        id: "vateud8-region-mismatch",
+        default_severity: Severity::Error,
+        description: "a position is listed under another VATEUD8 region than its FIR",
+    },
+    Rule {
+        id: "vateud8-not-found",
+        default_severity: Severity::Error,
+        description: "a position is missing from the VATEUD8 list",
+    },
+    Rule {
+        id: "vateud8-superfluous",
+        default_severity: Severity::Error,
+        description: "a VATEUD8 position is missing from the data",
+    },
+    Rule {
+        id: "vateud8-unused-ignore",
+        default_severity: Severity::Warning,
+        description: "an ignore_extra entry in the config matches no superfluous VATEUD8 position",
+    },
+    Rule {
+        id: "vateud8-skipped-row",
+        default_severity: Severity::Warning,
+        description: "a row of the VATEUD8 position list could not be parsed and was left out",
+    },
+    Rule {
+        id: "config-unknown-fir",
+        default_severity: Severity::Error,
+        description: "the config has an entry for a FIR without data folder",
+    },
+    Rule {
+        id: "config-missing-fir",
+        default_severity: Severity::Warning,
+        description: "a FIR has no entry in the config, its VATEUD8 checks are skipped",
+    },
+    Rule {
+        id: "config-unused-ignore",
+        default_severity: Severity::Warning,
+        description: "a vateud8_ignore entry in the config names no position of its FIR",
+    },
+    Rule {
+        id: "config-unknown-rule",
+        default_severity: Severity::Error,
+        description: "a lint level or suppression in the config names an unknown rule",
+    },
+    Rule {
+        id: "invalid-suppression",
+        default_severity: Severity::Error,
+        description:
+            "a suppression in the config has no reason or sets fir outside the global config",
+    },
+    Rule {
+        id: "stale-suppression",
+        default_severity: Severity::Warning,
+        description: "a suppression in the config does not match any finding",
+    },
+];
+
+/// Looks up a rule by id.
+#[must_use]
+pub fn rule(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.id == id)
+}
+
+/// A single finding of a check.
+#[derive(Clone, Debug, Serialize)]
+pub struct Diagnostic {
+    /// Stable identifier of the check, e.g. `duplicate-position`
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: String,
+    pub fir: Option<String>,
+    /// Id of the position, sector, airport or volume the finding is about
+    pub entity: Option<String>,
+    /// Data file the entity is defined in, relative to the repository root
+    pub file: Option<PathBuf>,
+    /// 1-based line of the entity definition in `file`, see [`locate`]
+    pub line: Option<usize>,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(rule: &'static str, message: String) -> Self {
+        Self {
+            rule,
+            severity: self::rule(rule).map_or(Severity::Error, |rule| rule.default_severity),
+            message,
+            fir: None,
+            entity: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    /// About `entity` of `fir` in `config.toml`
+    fn in_config(mut self, fir: Option<&str>, entity: String) -> Self {
+        self.file = Some("config.toml".into());
+        self.fir = fir.map(str::to_string);
+        self.entity = Some(entity);
+        self
+    }
+
+    /// About data file `file` of `fir` as a whole
+    fn in_file(mut self, fir: &str, file: &str) -> Self {
+        self.file = Some(Path::new("FIRs").join(fir).join(file));
+        self.fir = Some(fir.to_string());
+        self
+    }
+
+    fn at(mut self, fir: &str, file: &str, entity: &str) -> Self {
+        self = self.in_file(fir, file);
+        self.entity = Some(entity.to_string());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)?;
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, " ({}:{line})", file.display()),
+            (Some(file), None) => write!(f, " ({})", file.display()),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(e: &Error) -> Self {
+        let message = e.to_string();
+        match e {
+            Error::InvalidVolume(fir, volume, _) => {
+                Self::new("invalid-volume", message).at(fir, "volumes.geojson", volume)
+            }
+            Error::InvalidRunway(fir, icao, _) => {
+                Self::new("invalid-runway", message).at(fir, "airports.toml", icao)
+            }
+            Error::DuplicatePosition(fir, position, ..) => {
+                Self::new("duplicate-position", message).at(fir, "positions.toml", position)
+            }
+            Error::DuplicateAirport(fir, icao, ..) => {
+                Self::new("duplicate-airport", message).at(fir, "airports.toml", icao)
+            }
+            Error::InvalidPositionReference(InvalidPositionReferenceType::Sector, fir, id, ..) => {
+                Self::new("invalid-position-reference", message).at(fir, "sectors.toml", id)
+            }
+            Error::InvalidPositionReference(InvalidPositionReferenceType::Airport, fir, id, ..) => {
+                Self::new("invalid-position-reference", message).at(fir, "airports.toml", id)
+            }
+            Error::NonNeighbourReference(InvalidPositionReferenceType::Sector, fir, id, ..) => {
+                Self::new("non-neighbour-reference", message).at(fir, "sectors.toml", id)
+            }
+            Error::NonNeighbourReference(InvalidPositionReferenceType::Airport, fir, id, ..) => {
+                Self::new("non-neighbour-reference", message).at(fir, "airports.toml", id)
+            }
+            Error::UnknownUir(fir, _) => {
+                Self::new("unknown-uir", message).at(fir, "fir.toml", "uir")
+            }
+            Error::UnknownNeighbour(fir, neighbour) => {
+                Self::new("unknown-neighbour", message).at(fir, "fir.toml", neighbour)
+            }
+            Error::UnknownConfigFir(fir) => {
+                Self::new("config-unknown-fir", message).in_config(Some(fir), format!("firs.{fir}"))
+            }
+            Error::MissingFirConfig(fir) => {
+                Self::new("config-missing-fir", message).in_config(Some(fir), format!("firs.{fir}"))
+            }
+            Error::UnusedVateud8Ignore(fir, position) => {
+                Self::new("config-unused-ignore", message).in_config(Some(fir), position.clone())
+            }
+            Error::InvalidDataFile(fir, file, _) => {
+                Self::new("read-error", message).in_file(fir, file)
+            }
+            Error::FileRead(_)
+            | Error::FileWrite(_)
+            | Error::TomlDeserialize(_)
+            | Error::TomlSerialize(_)
+            | Error::ParseVolume(_)
+            | Error::UnknownFile(_) => Self::new("read-error", message),
+        }
+    }
+}
+
+impl From<&vateud8::Error> for Diagnostic {
+    fn from(e: &vateud8::Error) -> Self {
+        let message = e.to_string();
+        match e {
+            #[cfg(feature = "fetch")]
+            vateud8::Error::Fetch(_) => Self::new("vateud8-fetch", message),
+            vateud8::Error::TableNotFound
+            | vateud8::Error::Read(..)
+            | vateud8::Error::Write(..)
+            | vateud8::Error::ParseCsv(_)
+            | vateud8::Error::ParseJson(_) => Self::new("vateud8-fetch", message),
+            vateud8::Error::RegionMismatch(fir, position, ..) => {
+                Self::new("vateud8-region-mismatch", message).at(fir, "positions.toml", position)
+            }
+            vateud8::Error::NotFound(fir, position) => {
+                Self::new("vateud8-not-found", message).at(fir, "positions.toml", position)
+            }
+            vateud8::Error::UnusedIgnoreExtra(name) => {
+                Self::new("vateud8-unused-ignore", message).in_config(None, name.clone())
+            }
+            vateud8::Error::Superfluous(callsign) => Diagnostic {
+                entity: Some(callsign.clone()),
+                ..Self::new("vateud8-superfluous", message)
+            },
+        }
+    }
+}
+
+impl From<&vateud8::RowError> for Diagnostic {
+    fn from(e: &vateud8::RowError) -> Self {
+        Self::new("vateud8-skipped-row", e.to_string())
+    }
+}
+
+/// Fills in the line numbers of `diagnostics` by searching their files below `root` for the
+/// definition of the entity, i.e. a TOML table or key, or a `GeoJSON` feature id.
+#[cfg(feature = "fs")]
+pub fn locate(diagnostics: &mut [Diagnostic], root: &Path) {
+    locate_with(diagnostics, |file| read_to_string(root.join(file)).ok());
+}
+
+/// Like [`locate`], reading file contents by their path relative to the data set root with
+/// `read`.
+pub fn locate_with(diagnostics: &mut [Diagnostic], read: impl Fn(&Path) -> Option<String>) {
+    let mut files = HashMap::<PathBuf, Option<String>>::new();
+    for diagnostic in diagnostics {
+        let (Some(file), Some(entity)) = (&diagnostic.file, &diagnostic.entity) else {
+            continue;
+        };
+        let Some(contents) = files.entry(file.clone()).or_insert_with(|| read(file)) else {
+            continue;
+        };
+        diagnostic.line = find_definition(contents, entity).map(|idx| idx + 1);
+    }
+}
+
+/// 0-based index of the line defining `entity` in a data file, see [`locate`].
+#[must_use]
+pub fn find_definition(contents: &str, entity: &str) -> Option<usize> {
+    contents
+        .lines()
+        .position(|line| defines(line.trim(), entity))
+}
+
+fn defines(line: &str, entity: &str) -> bool {
+    let quoted = format!("\"{entity}\"");
+    [entity, quoted.as_str()].iter().any(|key| {
+        line.strip_prefix('[')
+            .and_then(|table| table.strip_prefix(key))
+            .is_some_and(|rest| rest.starts_with(']') || rest.starts_with('.'))
+            || line
+                .strip_prefix(key)
+                .is_some_and(|rest| rest.trim_start().starts_with('='))
+    }) || line
+        .strip_prefix("\"id\":")
+        .is_some_and(|rest| rest.trim().trim_end_matches(',') == quoted)
+}
+
+/// One JSON object per line.
+#[must_use]
+pub fn to_json_lines(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            serde_json::to_string(diagnostic).expect("diagnostics are always serializable") + "\n"
+        })
+        .collect()
+}
+
+/// Workflow commands GitHub Actions turns into annotations on the changed files.
+#[must_use]
+pub fn to_github(diagnostics: &[Diagnostic]) -> String {
+    let mut out = String::new();
+    for diagnostic in diagnostics {
+        let command = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "notice",
+        };
+        let subject = [diagnostic.fir.as_deref(), diagnostic.entity.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("-");
+        let title = if subject.is_empty() {
+            diagnostic.rule.to_string()
+        } else {
+            format!("{} ({subject})", diagnostic.rule)
+        };
+        let mut properties = vec![format!("title={}", escape_property(&title))];
+        if let Some(file) = &diagnostic.file {
+            properties.push(format!(
+                "file={}",
+                escape_property(&file.to_string_lossy().replace('\\', "/"))
+            ));
+        }
+        if let Some(line) = diagnostic.line {
+            properties.push(format!("line={line}"));
+        }
+        let _ = writeln!(
+            out,
+            "::{command} {}::{}",
+            properties.join(","),
+            escape_data(&diagnostic.message)
+        );
+    }
+    out
+}
+
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(property: &str) -> String {
+    escape_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// SARIF 2.1.0 log with a single run, as accepted by GitHub code scanning.
+#[must_use]
+pub fn to_sarif(diagnostics: &[Diagnostic]) -> Value {
+    let mut rules = diagnostics.iter().map(|d| d.rule).collect::<Vec<_>>();
+    rules.sort_unstable();
+    rules.dedup();
+
+    let results = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut result = json!({
+                "ruleId": diagnostic.rule,
+                "level": match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                    Severity::Info => "note",
+                },
+                "message": { "text": diagnostic.message },
+                "properties": { "fir": diagnostic.fir, "entity": diagnostic.entity },
+            });
+            if let Some(file) = &diagnostic.file {
+                let mut location = json!({
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file.to_string_lossy().replace('\\', "/") },
+                    },
+                });
+                if let Some(line) = diagnostic.line {
+                    location["physicalLocation"]["region"] = json!({ "startLine": line });
+                }
+                if let Some(entity) = &diagnostic.entity {
+                    location["logicalLocations"] = json!([{ "name": entity }]);
+                }
+                result["locations"] = json!([location]);
+            }
+            result
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{Diagnostic, Severity};
+    use crate::{vateud8, Error};
+
+    #[test]
+    fn test_formats() {
+        let mut diagnostics = [
+            Diagnostic::from(&Error::DuplicatePosition(
+                "EDMM".to_string(),
+                "MMN".to_string(),
+                "EDMM".to_string(),
+                "MMR".to_string(),
+            )),
+            Diagnostic::from(&vateud8::Error::Superfluous("EDXX_CTR".to_string())),
+        ];
+        super::locate_with(&mut diagnostics, |file| {
+            (file == Path::new("FIRs/EDMM/positions.toml")).then(|| {
+                "[MMR]\nfrequency = 129100000\n\n[\"MMN\"]\nfrequency = 129100000\n".to_string()
+            })
+        });
+
+        assert_eq!(diagnostics[0].rule, "duplicate-position");
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, Some(4));
+        assert_eq!(diagnostics[1].line, None);
+
+        let json_lines = super::to_json_lines(&diagnostics);
+        assert_eq!(json_lines.lines().count(), 2);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[duplicate-position]: Duplicate positions: EDMM-MMN, EDMM-MMR (FIRs/EDMM/positions.toml:4)"
+        );
+        assert!(json_lines.contains(r#""file":"FIRs/EDMM/positions.toml","line":4"#));
+
+        let github = super::to_github(&diagnostics);
+        assert!(github.starts_with(
+            "::error title=duplicate-position (EDMM-MMN),file=FIRs/EDMM/positions.toml,line=4::Duplicate positions: EDMM-MMN, EDMM-MMR\n"
+        ));
+        assert!(github.contains("::error title=vateud8-superfluous (EDXX_CTR)::"));
+
+        let sarif = super::to_sarif(&diagnostics);
+        assert_eq!(sarif["version"], "2.1.0");
+        let result = &sarif["runs"][0]["results"][0];
+        assert_eq!(result["ruleId"], "duplicate-position");
+        assert_eq!(
+            result["locations"][0]["physicalLocation"]["region"]["startLine"],
+            4
+        );
+        assert_eq!(
+            sarif["runs"][0]["tool"]["driver"]["rules"][1]["id"],
+            "vateud8-superfluous"
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Entity-level comparison of two data sets.
+
+use std::{collections::BTreeMap, fmt};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::OpenData;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub fir: String,
+    /// `position`, `sector`, `volume` or `airport`
+    pub entity: &'static str,
+    pub id: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = match self.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        };
+        write!(f, "{sign} {} {} {}", self.fir, self.entity, self.id)
+    }
+}
+
+/// Lists added, removed and changed entities from `old` to `new`, sorted by FIR, entity kind and
+/// id.
+#[must_use]
+pub fn diff(old: &OpenData, new: &OpenData) -> Vec<Change> {
+    let empty = crate::FIR::default();
+    old.firs
+        .keys()
+        .chain(new.firs.keys())
+        .unique()
+        .sorted()
+        .flat_map(|fir_name| {
+            let old_fir = old.firs.get(fir_name).unwrap_or(&empty);
+            let new_fir = new.firs.get(fir_name).unwrap_or(&empty);
+            diff_entities(fir_name, "airport", &old_fir.airports, &new_fir.airports)
+                .into_iter()
+                .chain(diff_entities(
+                    fir_name,
+                    "position",
+                    &old_fir.positions,
+                    &new_fir.positions,
+                ))
+                .chain(diff_entities(
+                    fir_name,
+                    "sector",
+                    &old_fir.sectors,
+                    &new_fir.sectors,
+                ))
+                .chain(diff_entities(
+                    fir_name,
+                    "volume",
+                    &old_fir.volumes,
+                    &new_fir.volumes,
+                ))
+        })
+        .collect()
+}
+
+fn diff_entities<T: Serialize>(
+    fir: &str,
+    entity: &'static str,
+    old: &BTreeMap<String, T>,
+    new: &BTreeMap<String, T>,
+) -> Vec<Change> {
+    old.keys()
+        .chain(new.keys())
+        .unique()
+        .sorted()
+        .filter_map(|id| {
+            let kind = match (old.get(id), new.get(id)) {
+                (Some(_), None) => ChangeKind::Removed,
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(old), Some(new))
+                    if serde_json::to_value(old).ok() != serde_json::to_value(new).ok() =>
+                {
+                    ChangeKind::Changed
+                }
+                _ => return None,
+            };
+            Some(Change {
+                kind,
+                fir: fir.to_string(),
+                entity,
+                id: id.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, ChangeKind};
+    use crate::OpenData;
+
+    #[test]
+    fn test_diff() {
+        let mut old = OpenData::default();
+        old.firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        old.firs
+            .insert("EDGG".to_string(), crate::tests::euroscope_fir());
+        assert!(diff(&old, &old).is_empty());
+
+        let mut new = OpenData::default();
+        new.firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        new.firs
+            .insert("LOVV".to_string(), crate::tests::euroscope_fir());
+        let edmm = new.firs.get_mut("EDMM").unwrap();
+        edmm.positions.get_mut("MMR").unwrap().frequency = 133_680_000;
+        edmm.volumes.remove("TEST");
+        let position = edmm.positions["MMR"].clone();
+        edmm.positions.insert("MMN".to_string(), position);
+
+        let changes = diff(&old, &new);
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "- EDGG airport EDDM",
+                "- EDGG position MMR",
+                "- EDGG sector TEST",
+                "- EDGG volume TEST",
+                "+ EDMM position MMN",
+                "~ EDMM position MMR",
+                "- EDMM volume TEST",
+                "+ LOVV airport EDDM",
+                "+ LOVV position MMR",
+                "+ LOVV sector TEST",
+                "+ LOVV volume TEST",
+            ]
+        );
+        assert_eq!(changes[5].kind, ChangeKind::Changed);
+    }
+}
@@ -0,0 +1,503 @@
+//! Import of `EuroScope` sector files to bootstrap a FIR folder.
+//!
+//! From the `.ese` file `[POSITIONS]` become [`Position`]s keyed by their identifier and each
+//! `SECTOR` of `[AIRSPACE]` becomes a [`Volume`] built from its `BORDER` sector lines plus a
+//! [`Sector`] with the `OWNER` list as position priority. From the optional `.sct` file
+//! `[AIRPORT]` and `[RUNWAY]` become [`Airport`]s.
+
+use std::{collections::BTreeMap, io};
+#[cfg(feature = "fs")]
+use std::{fs, path::Path};
+
+use geo::{point, Coord, Distance, Euclidean, LineString, Point, Polygon};
+use itertools::Itertools;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{
+    geometry::{self, METERS_PER_NAUTICAL_MILE},
+    position::UnknownStationType,
+    Airport, Position, PositionReference, Runway, Sector, Volume, FIR,
+};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to read file: {0}")]
+    FileRead(#[from] io::Error),
+    #[error("line {0}: expected at least {1} fields")]
+    MissingFields(usize, usize),
+    #[error("line {0}: invalid coordinate {1:?}")]
+    InvalidCoordinate(usize, String),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: {1}")]
+    StationType(usize, UnknownStationType),
+    #[error("line {0}: {1} outside of SECTORLINE or SECTOR")]
+    Orphan(usize, String),
+    #[error("invalid arc resolution {0}, must be a positive number of degrees")]
+    InvalidResolution(f64),
+    #[error("line {0}: circle around airport {1}, which is not in the [AIRPORT] section of the .sct file")]
+    UnknownAirport(usize, String),
+}
+
+enum SectorLine {
+    Line(Vec<Coord>),
+    Circle(Point, f64),
+}
+
+#[derive(Default)]
+struct EseSector {
+    name: String,
+    lower_level: u64,
+    upper_level: u64,
+    owners: Vec<String>,
+    borders: Vec<String>,
+}
+
+/// Reads an `.ese` and optionally an `.sct` file, see [`import_str`].
+#[cfg(feature = "fs")]
+pub fn import(ese: &Path, sct: Option<&Path>, resolution: f64) -> Result<FIR, Error> {
+    // sector files are commonly not UTF-8 encoded
+    let read = |path: &Path| -> Result<String, Error> {
+        Ok(String::from_utf8_lossy(&fs::read(path)?).into_owned())
+    };
+    let sct = sct.map(read).transpose()?;
+    import_str(&read(ese)?, sct.as_deref(), resolution)
+}
+
+/// Converts sector file contents into FIR data, approximating circle sector lines with a point
+/// at least every `resolution` degrees.
+///
+/// Sectors whose border cannot be assembled are skipped with a warning.
+pub fn import_str(ese: &str, sct: Option<&str>, resolution: f64) -> Result<FIR, Error> {
+    if !geometry::valid_resolution(resolution) {
+        return Err(Error::InvalidResolution(resolution));
+    }
+    let mut fir = FIR {
+        airports: sct.map(airports).transpose()?.unwrap_or_default(),
+        ..Default::default()
+    };
+    let mut sector_lines = BTreeMap::new();
+    let mut sectors = vec![];
+    let mut current_line: Option<(String, Vec<Coord>)> = None;
+
+    for (section, line_no, fields) in sections(ese) {
+        match (section, fields[0]) {
+            ("POSITIONS", _) => {
+                let (id, position) = position(line_no, &fields)?;
+                fir.positions.insert(id, position);
+            }
+            ("AIRSPACE", "SECTORLINE") => {
+                if let Some((name, coords)) = current_line.take() {
+                    sector_lines.insert(name, SectorLine::Line(coords));
+                }
+                current_line = Some((field(line_no, &fields, 1)?.to_string(), vec![]));
+            }
+            ("AIRSPACE", "COORD") => {
+                let (_, coords) = current_line
+                    .as_mut()
+                    .ok_or_else(|| Error::Orphan(line_no, "COORD".to_string()))?;
+                coords.push(coord(
+                    line_no,
+                    field(line_no, &fields, 1)?,
+                    field(line_no, &fields, 2)?,
+                )?);
+            }
+            ("AIRSPACE", "CIRCLE_SECTORLINE") => {
+                // centred on coordinates or, with four fields, on an airport of the .sct file
+                let (center, radius) = if fields.len() >= 5 {
+                    (coord(line_no, fields[2], fields[3])?.into(), fields[4])
+                } else {
+                    let icao = field(line_no, &fields, 2)?;
+                    let airport = fir
+                        .airports
+                        .get(icao)
+                        .ok_or_else(|| Error::UnknownAirport(line_no, icao.to_string()))?;
+                    (airport.location, field(line_no, &fields, 3)?)
+                };
+                sector_lines.insert(
+                    field(line_no, &fields, 1)?.to_string(),
+                    SectorLine::Circle(center, number(line_no, radius)?),
+                );
+            }
+            ("AIRSPACE", "SECTOR") => {
+                if let Some((name, coords)) = current_line.take() {
+                    sector_lines.insert(name, SectorLine::Line(coords));
+                }
+                #[allow(
+                    clippy::cast_sign_loss,
+                    clippy::cast_possible_truncation,
+                    reason = "altitudes are positive and small"
+                )]
+                sectors.push(EseSector {
+                    name: field(line_no, &fields, 1)?.to_string(),
+                    lower_level: (number(line_no, field(line_no, &fields, 2)?)? / 100.0).round()
+                        as u64,
+                    upper_level: (number(line_no, field(line_no, &fields, 3)?)? / 100.0).round()
+                        as u64,
+                    ..Default::default()
+                });
+            }
+            ("AIRSPACE", keyword @ ("OWNER" | "BORDER")) => {
+                let sector = sectors
+                    .last_mut()
+                    .ok_or_else(|| Error::Orphan(line_no, keyword.to_string()))?;
+                let list = if keyword == "OWNER" {
+                    &mut sector.owners
+                } else {
+                    &mut sector.borders
+                };
+                list.extend(
+                    fields[1..]
+                        .iter()
+                        .filter(|entry| !entry.is_empty())
+                        .map(ToString::to_string),
+                );
+            }
+            _ => {}
+        }
+    }
+    if let Some((name, coords)) = current_line.take() {
+        sector_lines.insert(name, SectorLine::Line(coords));
+    }
+
+    for sector in sectors {
+        insert_sector(&mut fir, sector, &sector_lines, resolution);
+    }
+
+    Ok(fir)
+}
+
+fn insert_sector(
+    fir: &mut FIR,
+    sector: EseSector,
+    sector_lines: &BTreeMap<String, SectorLine>,
+    resolution: f64,
+) {
+    let Some(ring) = border_ring(&sector, sector_lines, resolution) else {
+        warn!(
+            "could not assemble border of sector {}, skipping",
+            sector.name
+        );
+        return;
+    };
+    fir.volumes.insert(
+        sector.name.clone(),
+        Volume::new(
+            sector.lower_level,
+            sector.upper_level,
+            Polygon::new(ring, vec![]),
+        ),
+    );
+    fir.sectors.insert(
+        sector.name.clone(),
+        Sector {
+            name: Some(sector.name.clone()),
+            volumes: vec![sector.name],
+            runway_filter: vec![],
+            position_priority: sector
+                .owners
+                .into_iter()
+                .map(|id| vec![PositionReference { fir: None, id }])
+                .collect(),
+        },
+    );
+}
+
+/// Iterates over the non-comment lines of a sector file as `(section, line number, fields)`,
+/// with fields split by `:` for `.ese` files.
+fn sections(input: &str) -> impl Iterator<Item = (&str, usize, Vec<&str>)> {
+    let mut section = "";
+    input.lines().enumerate().filter_map(move |(idx, line)| {
+        let line = line.split(';').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name;
+            return None;
+        }
+        Some((section, idx + 1, line.split(':').map(str::trim).collect()))
+    })
+}
+
+fn field<'a>(line_no: usize, fields: &[&'a str], idx: usize) -> Result<&'a str, Error> {
+    fields
+        .get(idx)
+        .copied()
+        .ok_or(Error::MissingFields(line_no, idx + 1))
+}
+
+fn number(line_no: usize, value: &str) -> Result<f64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::InvalidNumber(line_no, value.to_string()))
+}
+
+fn position(line_no: usize, fields: &[&str]) -> Result<(String, Position), Error> {
+    if fields.len() < 7 {
+        return Err(Error::MissingFields(line_no, 7));
+    }
+    let khz = (number(line_no, fields[2])? * 1_000.0).round();
+    if !(0.0..=f64::from(u32::MAX / 1000)).contains(&khz) {
+        return Err(Error::InvalidNumber(line_no, fields[2].to_string()));
+    }
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        reason = "checked to be positive and in range"
+    )]
+    let frequency = khz as u32 * 1000;
+    Ok((
+        fields[3].to_string(),
+        Position {
+            frequency,
+            prefix: fields[5].to_string(),
+            station_type: fields[6]
+                .parse()
+                .map_err(|e| Error::StationType(line_no, e))?,
+            name: Some(fields[0].to_string()).filter(|name| !name.is_empty()),
+            radio_callsign: fields[1].to_string(),
+            cpdlc_logon: None,
+            airspace_groups: vec![],
+            gcap_tier: None,
+        },
+    ))
+}
+
+/// Parses `EuroScope` coordinates like `N048.21.13.000` `E011.47.10.000`.
+fn coord(line_no: usize, lat: &str, lon: &str) -> Result<Coord, Error> {
+    fn part(value: &str, positive: char, negative: char) -> Option<f64> {
+        let mut chars = value.chars();
+        let sign = match chars.next()?.to_ascii_uppercase() {
+            c if c == positive => 1.0,
+            c if c == negative => -1.0,
+            _ => return None,
+        };
+        let (deg, min, sec, frac) = chars.as_str().split('.').collect_tuple()?;
+        let seconds = format!("{sec}.{frac}").parse::<f64>().ok()?;
+        Some(sign * (deg.parse::<f64>().ok()? + min.parse::<f64>().ok()? / 60.0 + seconds / 3600.0))
+    }
+
+    Ok(Coord {
+        x: part(lon, 'E', 'W').ok_or_else(|| Error::InvalidCoordinate(line_no, lon.to_string()))?,
+        y: part(lat, 'N', 'S').ok_or_else(|| Error::InvalidCoordinate(line_no, lat.to_string()))?,
+    })
+}
+
+/// Chains the border sector lines of a sector into a closed ring, reversing lines as needed so
+/// each one starts where the previous ended.
+fn border_ring(
+    sector: &EseSector,
+    sector_lines: &BTreeMap<String, SectorLine>,
+    resolution: f64,
+) -> Option<LineString> {
+    let lines = sector
+        .borders
+        .iter()
+        .map(|name| sector_lines.get(name))
+        .collect::<Option<Vec<_>>>()?;
+
+    if let [SectorLine::Circle(center, radius)] = lines[..] {
+        return Some(geometry::circle(
+            *center,
+            radius * METERS_PER_NAUTICAL_MILE,
+            resolution,
+        ));
+    }
+
+    let mut ring: Vec<Coord> = vec![];
+    for (idx, line) in lines.iter().enumerate() {
+        let SectorLine::Line(coords) = line else {
+            return None;
+        };
+        let (first, last) = (*coords.first()?, *coords.last()?);
+        let distance = |a: Coord, b: Coord| Euclidean::distance(Point::from(a), Point::from(b));
+        let reverse = match ring.last() {
+            Some(end) => distance(*end, last) < distance(*end, first),
+            // orient the first line towards the second one
+            None => match lines.get(idx + 1) {
+                Some(SectorLine::Line(next)) => {
+                    let (next_first, next_last) = (*next.first()?, *next.last()?);
+                    distance(first, next_first).min(distance(first, next_last))
+                        < distance(last, next_first).min(distance(last, next_last))
+                }
+                _ => false,
+            },
+        };
+        let oriented: Box<dyn Iterator<Item = &Coord>> = if reverse {
+            Box::new(coords.iter().rev())
+        } else {
+            Box::new(coords.iter())
+        };
+        for coord in oriented {
+            if ring.last() != Some(coord) {
+                ring.push(*coord);
+            }
+        }
+    }
+
+    let mut ring = LineString::new(ring);
+    ring.close();
+    (ring.0.len() >= 4).then_some(ring)
+}
+
+fn airports(sct: &str) -> Result<BTreeMap<String, Airport>, Error> {
+    let mut airports = BTreeMap::new();
+    let mut runways: BTreeMap<String, (Option<String>, Vec<Runway>)> = BTreeMap::new();
+
+    for (section, line_no, line) in sections(sct) {
+        let fields = line
+            .iter()
+            .flat_map(|part| part.split_whitespace())
+            .collect::<Vec<_>>();
+        match section {
+            "AIRPORT" => {
+                if fields.len() < 4 {
+                    return Err(Error::MissingFields(line_no, 4));
+                }
+                let location = coord(line_no, fields[2], fields[3])?;
+                airports.insert(
+                    fields[0].to_string(),
+                    Airport {
+                        name: fields[0].to_string(),
+                        iata_designator: None,
+                        fallback_prefixes: vec![],
+                        location: point!(location),
+                        elevation: None,
+                        position_priority: vec![],
+                        runways: vec![],
+                    },
+                );
+            }
+            "RUNWAY" if fields.len() >= 9 => {
+                let (name, airport_runways) = runways.entry(fields[8].to_string()).or_default();
+                if name.is_none() && fields.len() > 9 {
+                    *name = Some(fields[9..].join(" "));
+                }
+                airport_runways.push(Runway {
+                    designator: fields[0].to_string(),
+                    reciprocal: Some(fields[1].to_string()),
+                    threshold: Some(coord(line_no, fields[4], fields[5])?.into()),
+                    reciprocal_threshold: Some(coord(line_no, fields[6], fields[7])?.into()),
+                    ..Default::default()
+                });
+            }
+            _ => {}
+        }
+    }
+
+    for (icao, (name, airport_runways)) in runways {
+        if let Some(airport) = airports.get_mut(&icao) {
+            if let Some(name) = name {
+                airport.name = name;
+            }
+            airport.runways = airport_runways;
+        }
+    }
+
+    Ok(airports)
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::{Contains, Point};
+
+    use crate::{position::StationType, DEFAULT_ARC_RESOLUTION};
+
+    const ESE: &str = "
+[POSITIONS]
+Muenchen Director South:München Director:132.305:DMSD:S:EDDM:APP:-:-:2201:2277
+Muenchen Radar:München Radar:129.100:MMR:R:EDMM:CTR:-:-:2201:2277
+
+[AIRSPACE]
+SECTORLINE:SOUTH
+COORD:N048.00.00.000:E011.00.00.000
+COORD:N048.00.00.000:E012.00.00.000
+SECTORLINE:EAST
+COORD:N049.00.00.000:E012.00.00.000
+COORD:N048.00.00.000:E012.00.00.000
+SECTORLINE:NORTHWEST
+COORD:N049.00.00.000:E012.00.00.000
+COORD:N049.00.00.000:E011.00.00.000
+COORD:N048.00.00.000:E011.00.00.000
+
+SECTOR:EDMM_TEST:0:24500
+OWNER:DMSD:MMR
+BORDER:SOUTH:EAST:NORTHWEST
+";
+
+    const SCT: &str = "
+[AIRPORT]
+EDDM 118.705 N048.21.13.000 E011.47.10.000 D
+
+[RUNWAY]
+08L 26R 082 262 N048.20.25.000 E011.45.03.000 N048.20.51.000 E011.48.13.000 EDDM München
+";
+
+    #[test]
+    fn test_import() {
+        let fir = super::import_str(ESE, Some(SCT), DEFAULT_ARC_RESOLUTION).unwrap();
+        assert!(matches!(
+            super::import_str(ESE, Some(SCT), f64::INFINITY),
+            Err(super::Error::InvalidResolution(_))
+        ));
+
+        let dmsd = &fir.positions["DMSD"];
+        assert_eq!(dmsd.frequency, 132_305_000);
+        assert_eq!(dmsd.prefix, "EDDM");
+        assert_eq!(dmsd.station_type, StationType::Approach);
+        assert_eq!(fir.positions["MMR"].station_type, StationType::Center);
+        for frequency in ["5000.000", "-118.000"] {
+            let fields = ["", "", frequency, "MMR", "R", "EDMM", "CTR"];
+            assert!(matches!(
+                super::position(1, &fields),
+                Err(super::Error::InvalidNumber(1, _))
+            ));
+        }
+
+        let volume = &fir.volumes["EDMM_TEST"];
+        assert_eq!(volume.lower_level(), 0);
+        assert_eq!(volume.upper_level(), 245);
+        assert!(volume.lateral_bounds().contains(&Point::new(11.5, 48.5)));
+        assert_eq!(volume.lateral_bounds().exterior().0.len(), 5);
+
+        let sector = &fir.sectors["EDMM_TEST"];
+        assert_eq!(sector.volumes, vec!["EDMM_TEST"]);
+        assert_eq!(
+            sector
+                .position_priority
+                .iter()
+                .map(|tier| tier[0].id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["DMSD", "MMR"]
+        );
+
+        let eddm = &fir.airports["EDDM"];
+        assert_eq!(eddm.name, "München");
+        let runway = eddm.runway("26R").unwrap();
+        assert_eq!(runway.designator, "08L");
+        assert!(runway.check().is_ok());
+        assert!((75.0..85.0).contains(&runway.true_heading().unwrap()));
+        assert!((3_900.0..4_100.0).contains(&runway.length().unwrap()));
+    }
+
+    #[test]
+    fn test_airport_circle() {
+        let ese = "[AIRSPACE]\nCIRCLE_SECTORLINE:EDDM_CTR:EDDM:10\nSECTOR:EDDM_CTR:0:4000\nBORDER:EDDM_CTR\n";
+        let fir = super::import_str(ese, Some(SCT), DEFAULT_ARC_RESOLUTION).unwrap();
+        let bounds = fir.volumes["EDDM_CTR"].lateral_bounds();
+        assert!(bounds.contains(&fir.airports["EDDM"].location));
+        assert!(bounds.contains(&Point::new(11.786, 48.5)));
+        assert!(!bounds.contains(&Point::new(11.786, 48.6)));
+
+        assert!(matches!(
+            super::import_str(ese, None, DEFAULT_ARC_RESOLUTION),
+            Err(super::Error::UnknownAirport(2, icao)) if icao == "EDDM"
+        ));
+        assert!(matches!(
+            super::import_str("[AIRSPACE]\nCIRCLE_SECTORLINE:X:EDDM\n", Some(SCT), 1.0),
+            Err(super::Error::MissingFields(2, 4))
+        ));
+    }
+}
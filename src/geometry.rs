@@ -0,0 +1,59 @@
+use geo::{Coord, Destination, Haversine, LineString, Point};
+
+pub(crate) const METERS_PER_NAUTICAL_MILE: f64 = 1852.0;
+pub(crate) const METERS_PER_FOOT: f64 = 0.3048;
+
+/// Whether `resolution` is usable as step in degrees for [`arc`] and [`circle`]
+pub(crate) fn valid_resolution(resolution: f64) -> bool {
+    resolution.is_finite() && resolution > 0.0
+}
+
+/// Approximates an arc around `center` with `radius` meters from bearing `start` to `end` in
+/// degrees, producing at least one point every `resolution` degrees, both ends included.
+pub(crate) fn arc(
+    center: Point,
+    radius: f64,
+    start: f64,
+    end: f64,
+    clockwise: bool,
+    resolution: f64,
+) -> Vec<Coord> {
+    let (sweep, direction) = if clockwise {
+        ((end - start).rem_euclid(360.0), 1.0)
+    } else {
+        ((start - end).rem_euclid(360.0), -1.0)
+    };
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        reason = "sweep is in 0..360 and resolution positive"
+    )]
+    let steps = (sweep / resolution).ceil().max(1.0) as u32;
+    (0..=steps)
+        .map(|step| {
+            let bearing = start + direction * sweep * f64::from(step) / f64::from(steps);
+            Haversine::destination(center, bearing.rem_euclid(360.0), radius).into()
+        })
+        .collect()
+}
+
+/// Approximates a circle around `center` with `radius` meters as a closed ring.
+pub(crate) fn circle(center: Point, radius: f64, resolution: f64) -> LineString {
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        reason = "resolution is positive"
+    )]
+    let steps = (360.0 / resolution).ceil().max(3.0) as u32;
+    let mut ring = (0..steps)
+        .map(|step| {
+            Coord::from(Haversine::destination(
+                center,
+                360.0 * f64::from(step) / f64::from(steps),
+                radius,
+            ))
+        })
+        .collect::<LineString>();
+    ring.close();
+    ring
+}
@@ -0,0 +1,137 @@
+//! UIRs spanning several FIRs. A FIR belongs to the UIR named as `uir` in its `fir.toml`; the
+//! UIR itself is a folder in `FIRs/` like any FIR, holding the UIR-level positions, sectors and
+//! volumes, e.g. of `EURM_CTR`.
+
+use std::collections::BTreeMap;
+
+use geo::{BooleanOps, MultiPolygon};
+
+use crate::{
+    Airport, AirportIcao, FirName, OpenData, Position, PositionId, Sector, SectorId, Volume,
+    VolumeId, FIR,
+};
+
+impl OpenData {
+    /// UIR of FIR `fir_name`, if it declares one
+    #[must_use]
+    pub fn uir_of(&self, fir_name: &str) -> Option<&str> {
+        self.firs.get(fir_name)?.metadata.as_ref()?.uir.as_deref()
+    }
+
+    /// UIRs with their member FIRs, sorted by name
+    #[must_use]
+    pub fn uirs(&self) -> BTreeMap<&str, Vec<&FirName>> {
+        let mut uirs = BTreeMap::<_, Vec<_>>::new();
+        for fir_name in self.firs.keys() {
+            if let Some(uir) = self.uir_of(fir_name) {
+                uirs.entry(uir).or_default().push(fir_name);
+            }
+        }
+        uirs
+    }
+
+    /// The folder of UIR `uir`, if there is one, and its member FIRs
+    fn under_uir(&self, uir: &str) -> Vec<(&FirName, &FIR)> {
+        self.firs
+            .iter()
+            .filter(|(fir_name, _)| *fir_name == uir || self.uir_of(fir_name) == Some(uir))
+            .collect()
+    }
+
+    /// Volumes of UIR `uir` and its member FIRs, sorted by FIR and id
+    #[must_use]
+    pub fn uir_volumes(&self, uir: &str) -> Vec<(&FirName, &VolumeId, &Volume)> {
+        self.under_uir(uir)
+            .into_iter()
+            .flat_map(|(fir_name, fir)| fir.volumes.iter().map(move |(id, v)| (fir_name, id, v)))
+            .collect()
+    }
+
+    /// Positions of UIR `uir` and its member FIRs, sorted by FIR and id
+    #[must_use]
+    pub fn uir_positions(&self, uir: &str) -> Vec<(&FirName, &PositionId, &Position)> {
+        self.under_uir(uir)
+            .into_iter()
+            .flat_map(|(fir_name, fir)| fir.positions.iter().map(move |(id, p)| (fir_name, id, p)))
+            .collect()
+    }
+
+    /// Sectors of UIR `uir` and its member FIRs, sorted by FIR and id
+    #[must_use]
+    pub fn uir_sectors(&self, uir: &str) -> Vec<(&FirName, &SectorId, &Sector)> {
+        self.under_uir(uir)
+            .into_iter()
+            .flat_map(|(fir_name, fir)| fir.sectors.iter().map(move |(id, s)| (fir_name, id, s)))
+            .collect()
+    }
+
+    /// Airports of UIR `uir` and its member FIRs, sorted by FIR and ICAO designator
+    #[must_use]
+    pub fn uir_airports(&self, uir: &str) -> Vec<(&FirName, &AirportIcao, &Airport)> {
+        self.under_uir(uir)
+            .into_iter()
+            .flat_map(|(fir_name, fir)| fir.airports.iter().map(move |(id, a)| (fir_name, id, a)))
+            .collect()
+    }
+
+    /// Lateral boundary of UIR `uir` as union of all its volumes, empty for unknown UIRs
+    #[must_use]
+    pub fn uir_boundary(&self, uir: &str) -> MultiPolygon {
+        self.uir_volumes(uir).into_iter().fold(
+            MultiPolygon::new(vec![]),
+            |boundary, (_, _, volume)| {
+                boundary.union(&MultiPolygon::new(vec![volume.lateral_bounds().clone()]))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use geo::{polygon, Area, Contains, Point};
+
+    use crate::{OpenData, PositionReference, Volume, FIR};
+
+    #[test]
+    fn test_uir() {
+        let mut open_data = OpenData::default();
+        for fir_name in ["EDMM", "EDGG"] {
+            let mut fir = crate::tests::euroscope_fir();
+            fir.set_file("fir.toml", "uir = \"EDUU\"\n").unwrap();
+            open_data.firs.insert(fir_name.to_string(), fir);
+        }
+        // the other half of the square the EDMM triangle is part of
+        open_data.firs.get_mut("EDGG").unwrap().volumes = BTreeMap::from([(
+            "TEST".to_string(),
+            Volume::new(
+                0,
+                245,
+                polygon![(x: 11.0, y: 48.0), (x: 12.0, y: 49.0), (x: 11.0, y: 49.0)],
+            ),
+        )]);
+        open_data.firs.insert("EDUU".to_string(), FIR::default());
+        open_data.firs.insert("LKAA".to_string(), FIR::default());
+
+        // references within the UIR are allowed despite a restrictive neighbour list
+        let edmm = open_data.firs.get_mut("EDMM").unwrap();
+        edmm.set_file("fir.toml", "uir = \"EDUU\"\nneighbours = [\"LKAA\"]\n")
+            .unwrap();
+        edmm.sectors.get_mut("TEST").unwrap().position_priority = vec![vec![PositionReference {
+            fir: Some("EDGG".to_string()),
+            id: "MMR".to_string(),
+        }]];
+        assert!(open_data.metadata_checks().is_empty());
+
+        assert_eq!(open_data.uirs()["EDUU"], ["EDGG", "EDMM"]);
+        assert_eq!(open_data.uir_positions("EDUU").len(), 2);
+        assert_eq!(open_data.uir_airports("EDUU").len(), 2);
+        assert!(open_data.uir_volumes("LOVV").is_empty());
+
+        let boundary = open_data.uir_boundary("EDUU");
+        assert!(boundary.contains(&Point::new(11.2, 48.8)));
+        assert!((boundary.unsigned_area() - 1.0).abs() < 1e-9);
+        assert_eq!(boundary.0.len(), 1);
+    }
+}
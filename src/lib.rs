@@ -1,21 +1,53 @@
 mod airport;
+pub mod aixm;
+pub mod diagnostic;
+pub mod diff;
+pub mod euroscope;
+mod geometry;
+mod hierarchy;
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod metadata;
+pub mod openair;
 mod position;
+mod query;
+pub mod render;
 mod sector;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 pub mod vateud8;
 mod volume;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fs::read_to_string, io, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+};
+#[cfg(feature = "fs")]
+use std::{
+    fs::{self, read_to_string},
+    path::Path,
+};
 use thiserror::Error;
-use tracing::{info, warn};
+use tracing::info;
+#[cfg(feature = "fs")]
+use tracing::warn;
 
-pub use airport::Airport;
-pub use position::Position;
+use lint::LintConfig;
+
+pub use airport::{Airport, Runway, RunwayError, RunwayReference};
+pub use metadata::FirMetadata;
+pub use position::{GcapTier, Position, PositionReference, StationType};
 pub use sector::Sector;
-pub use volume::Volume;
+pub use volume::{insert_unique, Volume};
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub enum InvalidPositionReferenceType {
     Sector,
     Airport,
@@ -25,12 +57,22 @@ pub enum InvalidPositionReferenceType {
 pub enum Error {
     #[error("failed to read file: {0}")]
     FileRead(#[from] io::Error),
+    #[error("failed to write file: {0}")]
+    FileWrite(io::Error),
     #[error("failed to deserialize toml file: {0}")]
     TomlDeserialize(#[from] toml::de::Error),
+    #[error("failed to serialize toml file: {0}")]
+    TomlSerialize(#[from] toml::ser::Error),
     #[error("Invalid volumes: {0}")]
     ParseVolume(#[from] volume::ReadError),
+    #[error("unknown data file {0}")]
+    UnknownFile(String),
+    #[error("Invalid data file FIRs/{0}/{1}: {2}")]
+    InvalidDataFile(FirName, &'static str, Box<Error>),
     #[error("Invalid volumes: {0}, {1}, {2}")]
     InvalidVolume(FirName, VolumeId, volume::ConstraintError),
+    #[error("Invalid runway: {0}, {1}, {2}")]
+    InvalidRunway(FirName, AirportIcao, RunwayError),
     #[error("Duplicate positions: {0}-{1}, {2}-{3}")]
     DuplicatePosition(FirName, PositionId, FirName, PositionId),
     #[error(
@@ -54,6 +96,74 @@ pub enum Error {
         FirName,
         PositionId,
     ),
+    #[error("Position reference to non-neighbouring FIR: {3}-{4} (in {0:?} {1}-{2})")]
+    NonNeighbourReference(
+        InvalidPositionReferenceType,
+        FirName,
+        String,
+        FirName,
+        PositionId,
+    ),
+    #[error("FIR {0} belongs to unknown UIR {1}")]
+    UnknownUir(FirName, FirName),
+    #[error("FIR {0} declares unknown neighbour {1}")]
+    UnknownNeighbour(FirName, FirName),
+    #[error("Config for unknown FIR {0}")]
+    UnknownConfigFir(FirName),
+    #[error("FIR {0} has no config entry")]
+    MissingFirConfig(FirName),
+    #[error("vateud8_ignore of FIR {0} lists unknown position {1}")]
+    UnusedVateud8Ignore(FirName, PositionId),
+}
+
+/// Default step in degrees when approximating arcs and circles by polygons
+pub const DEFAULT_ARC_RESOLUTION: f64 = 5.0;
+
+impl Error {
+    /// FIRs an error concerns, empty for errors not tied to data
+    #[must_use]
+    pub fn firs(&self) -> Vec<&str> {
+        match self {
+            Self::InvalidVolume(fir, ..)
+            | Self::InvalidRunway(fir, ..)
+            | Self::UnknownConfigFir(fir)
+            | Self::MissingFirConfig(fir)
+            | Self::UnusedVateud8Ignore(fir, _)
+            | Self::UnknownUir(fir, _)
+            | Self::UnknownNeighbour(fir, _)
+            | Self::InvalidDataFile(fir, ..) => vec![fir],
+            Self::DuplicatePosition(fir1, _, fir2, _)
+            | Self::DuplicateAirport(fir1, _, _, fir2, _, _)
+            | Self::InvalidPositionReference(_, fir1, _, fir2, _)
+            | Self::NonNeighbourReference(_, fir1, _, fir2, _) => vec![fir1, fir2],
+            _ => vec![],
+        }
+    }
+}
+
+/// Maps `items`, in parallel with the `parallel` feature, keeping their order.
+fn map_ordered<T: Send, R: Send>(items: Vec<T>, f: impl Fn(T) -> R + Sync + Send) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+        items.into_par_iter().map(f).collect()
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.into_iter().map(f).collect()
+    }
+}
+
+/// Runs `a` and `b`, in parallel with the `parallel` feature.
+fn join<A: Send, B: Send>(a: impl FnOnce() -> A + Send, b: impl FnOnce() -> B + Send) -> (A, B) {
+    #[cfg(feature = "parallel")]
+    {
+        rayon::join(a, b)
+    }
+    #[cfg(not(feature = "parallel"))]
+    {
+        (a(), b())
+    }
 }
 
 type FirName = String;
@@ -64,44 +174,137 @@ type VolumeId = String;
 
 #[derive(Default, Serialize)]
 pub struct FIR {
-    pub airports: HashMap<AirportIcao, Airport>,
-    pub positions: HashMap<PositionId, Position>,
-    pub sectors: HashMap<SectorId, Sector>,
-    pub volumes: HashMap<VolumeId, Volume>,
+    pub airports: BTreeMap<AirportIcao, Airport>,
+    pub positions: BTreeMap<PositionId, Position>,
+    pub sectors: BTreeMap<SectorId, Sector>,
+    pub volumes: BTreeMap<VolumeId, Volume>,
+    /// From `fir.toml`, if the FIR has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<FirMetadata>,
 }
 
 impl FIR {
-    // TODO propagate errors? not found files ok/allowlist,
-    fn from_folder(path: &Path) -> Self {
-        let positions = Position::from_toml(&path.join("positions.toml")).unwrap_or_else(|e| {
-            info!(
-                "Could not receive position data from {}: {e}",
-                path.display()
-            );
-            HashMap::default()
-        });
-        let sectors = Sector::from_toml(&path.join("sectors.toml")).unwrap_or_else(|e| {
-            info!("Could not receive sector data from {}: {e}", path.display());
-            HashMap::default()
-        });
-        let volumes = Volume::from_geojson(&path.join("volumes.geojson")).unwrap_or_else(|e| {
-            info!("Could not receive volume data from {}: {e}", path.display());
-            HashMap::default()
-        });
-        let airports = Airport::from_toml(&path.join("airports.toml")).unwrap_or_else(|e| {
-            info!(
-                "Could not receive airport data from {}: {e}",
-                path.display()
-            );
-            HashMap::default()
-        });
+    /// Reads the data files in `path`, logging those that cannot be read or parsed and leaving
+    /// their entities empty. Missing files are empty.
+    #[cfg(feature = "fs")]
+    #[must_use]
+    pub fn from_folder(path: &Path) -> Self {
+        let (fir, errors) = Self::read_folder(path);
+        for (name, e) in errors {
+            warn!("Could not read {}: {e}", path.join(name).display());
+        }
+        fir
+    }
+
+    /// Like [`FIR::from_folder`], but fails if a data file cannot be read or parsed instead of
+    /// leaving its entities empty. Missing files are empty.
+    #[cfg(feature = "fs")]
+    pub fn try_from_folder(path: &Path) -> Result<Self, Error> {
+        let (fir, errors) = Self::read_folder(path);
+        match errors.into_iter().next() {
+            Some((_, e)) => Err(e),
+            None => Ok(fir),
+        }
+    }
 
-        Self {
-            airports,
-            positions,
-            sectors,
+    /// Like [`FIR::from_folder`], returning the files that cannot be read or parsed with their
+    /// errors instead of logging them
+    #[cfg(feature = "fs")]
+    #[must_use]
+    pub fn read_folder(path: &Path) -> (Self, Vec<(&'static str, Error)>) {
+        let mut fir = Self::default();
+        let mut errors = vec![];
+        for name in [
+            "fir.toml",
+            "positions.toml",
+            "sectors.toml",
+            "airports.toml",
+            "volumes.geojson",
+        ] {
+            let result = match read_to_string(path.join(name)) {
+                Ok(contents) => fir.set_file(name, &contents),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                errors.push((name, e));
+            }
+        }
+        (fir, errors)
+    }
+
+    /// Builds a FIR from in-memory data files as returned by [`FIR::to_files`], missing files
+    /// are empty.
+    pub fn from_files<'a>(
+        files: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, Error> {
+        let mut fir = Self::default();
+        for (name, contents) in files {
+            fir.set_file(name, contents)?;
+        }
+        Ok(fir)
+    }
+
+    /// Replaces the entities of data file `name`, e.g. `positions.toml`, by parsing `contents`.
+    pub fn set_file(&mut self, name: &str, contents: &str) -> Result<(), Error> {
+        match name {
+            "positions.toml" => self.positions = Position::from_toml_str(contents)?,
+            "sectors.toml" => self.sectors = Sector::from_toml_str(contents)?,
+            "airports.toml" => self.airports = Airport::from_toml_str(contents)?,
+            "volumes.geojson" => self.volumes = Volume::from_geojson_str(contents, name)?,
+            "fir.toml" => self.metadata = Some(FirMetadata::from_toml_str(contents)?),
+            _ => return Err(Error::UnknownFile(name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Serializes the data files read by `from_folder` as `(file name, contents)`, skipping
+    /// empty ones. Entries are sorted by id, comments of the original files are not preserved.
+    pub fn to_files(&self) -> Result<Vec<(&'static str, String)>, Error> {
+        fn toml_file<T: Serialize>(
+            name: &'static str,
+            entries: &BTreeMap<String, T>,
+        ) -> Result<Option<(&'static str, String)>, Error> {
+            if entries.is_empty() {
+                return Ok(None);
+            }
+            Ok(Some((name, toml::to_string(entries)?)))
+        }
+
+        let volumes = if self.volumes.is_empty() {
+            None
+        } else {
+            let mut geojson = serde_json::to_string_pretty(&Volume::to_geojson(&self.volumes))
+                .expect("geojson is always serializable");
+            geojson.push('\n');
+            Some(("volumes.geojson", geojson))
+        };
+
+        let metadata = match &self.metadata {
+            Some(metadata) => Some(("fir.toml", toml::to_string(metadata)?)),
+            None => None,
+        };
+
+        Ok([
+            metadata,
+            toml_file("positions.toml", &self.positions)?,
+            toml_file("sectors.toml", &self.sectors)?,
+            toml_file("airports.toml", &self.airports)?,
             volumes,
+        ]
+        .into_iter()
+        .flatten()
+        .collect())
+    }
+
+    /// Writes the files of [`FIR::to_files`] into `path`.
+    #[cfg(feature = "fs")]
+    pub fn to_folder(&self, path: &Path) -> Result<(), Error> {
+        fs::create_dir_all(path).map_err(Error::FileWrite)?;
+        for (name, contents) in self.to_files()? {
+            fs::write(path.join(name), contents).map_err(Error::FileWrite)?;
         }
+        Ok(())
     }
 
     fn run_checks(&self) -> Result<(), Vec<(&String, volume::ConstraintError)>> {
@@ -109,6 +312,7 @@ impl FIR {
             .volumes
             .iter()
             .filter_map(|(id, vol)| vol.check_level().map_err(|e| (id, e)).err())
+            .sorted_by_key(|(id, _)| *id)
             .collect::<Vec<_>>();
         if errs.is_empty() {
             Ok(())
@@ -119,12 +323,27 @@ impl FIR {
 }
 
 #[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     vateud8: Vateud8Config,
-    firs: HashMap<FirName, FirConfig>,
+    firs: BTreeMap<FirName, FirConfig>,
+    #[serde(default)]
+    lints: LintConfig,
+}
+
+impl Config {
+    #[cfg(feature = "fs")]
+    pub fn from_toml(path: &Path) -> Result<Self, Error> {
+        Self::from_toml_str(&read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, Error> {
+        Ok(toml::from_str(contents)?)
+    }
 }
 
 #[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Vateud8Config {
     #[serde(default)]
     ignore_regions: Vec<u32>,
@@ -133,43 +352,85 @@ pub struct Vateud8Config {
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct FirConfig {
     vateud8_region: Option<u32>,
     #[serde(default)]
     vateud8_ignore: Vec<String>,
     #[serde(default)]
     optional_frequency: bool,
+    #[serde(default)]
+    lints: LintConfig,
 }
 
 #[derive(Default, Serialize)]
 pub struct OpenData {
-    pub firs: HashMap<FirName, FIR>,
+    pub firs: BTreeMap<FirName, FIR>,
     pub config: Config,
 }
 
 impl OpenData {
+    /// Reads the data set below `path`, failing on data files that cannot be read or parsed
+    #[cfg(feature = "fs")]
     pub fn from_path(path: &Path) -> Result<Self, Error> {
-        Ok(Self {
-            firs: path
-                .join("FIRs")
+        let (open_data, errors) = Self::from_path_with_errors(path)?;
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(open_data),
+        }
+    }
+
+    /// Like [`OpenData::from_path`], but loads FIRs with data files that cannot be read or
+    /// parsed with their entities empty, returning an [`Error::InvalidDataFile`] for each file
+    #[cfg(feature = "fs")]
+    pub fn from_path_with_errors(path: &Path) -> Result<(Self, Vec<Error>), Error> {
+        let mut firs = map_ordered(
+            path.join("FIRs")
                 .read_dir()?
-                .filter_map(|fir_folder| {
-                    match fir_folder.map(|folder| {
-                        (
-                            folder.file_name().to_string_lossy().to_string(),
-                            FIR::from_folder(&folder.path()),
-                        )
-                    }) {
-                        Ok(fir_entry) => Some(fir_entry),
-                        Err(e) => {
-                            warn!("{e}");
-                            None
-                        }
-                    }
-                })
+                .filter_map(|fir_folder| fir_folder.map_err(|e| warn!("{e}")).ok())
                 .collect(),
-            config: toml::from_str(&read_to_string(path.join("config.toml"))?)?,
-        })
+            |folder| {
+                let fir_name = folder.file_name().to_string_lossy().to_string();
+                let (fir, errors) = FIR::read_folder(&folder.path());
+                let errors = errors
+                    .into_iter()
+                    .map(|(file, e)| Error::InvalidDataFile(fir_name.clone(), file, Box::new(e)))
+                    .collect::<Vec<_>>();
+                (fir_name, fir, errors)
+            },
+        );
+        firs.sort_by(|(a, ..), (b, ..)| a.cmp(b));
+        let mut errors = vec![];
+        let firs = firs
+            .into_iter()
+            .map(|(fir_name, fir, fir_errors)| {
+                errors.extend(fir_errors);
+                (fir_name, fir)
+            })
+            .collect::<BTreeMap<_, _>>();
+        let open_data = Self {
+            firs,
+            config: Config::from_toml(&path.join("config.toml"))?,
+        };
+        Ok((open_data, errors))
+    }
+
+    /// Looks up the position a reference in FIR `fir_name` points to, references without FIR
+    /// point into `fir_name` itself.
+    #[must_use]
+    pub fn resolve(&self, fir_name: &str, pos_ref: &PositionReference) -> Option<&Position> {
+        self.firs
+            .get(pos_ref.fir.as_deref().unwrap_or(fir_name))
+            .and_then(|fir| fir.positions.get(&pos_ref.id))
+    }
+
+    /// Looks up the runway a runway filter entry points to, in the airport of that ICAO
+    /// designator in any FIR.
+    #[must_use]
+    pub fn runway(&self, runway_ref: &RunwayReference) -> Option<&Runway> {
+        self.airports()
+            .filter(|(_, icao, _)| **icao == runway_ref.icao)
+            .find_map(|(_, _, airport)| airport.runway(&runway_ref.designator))
     }
 
     fn positions(&self) -> impl Iterator<Item = (&FirName, &PositionId, &Position)> {
@@ -196,24 +457,17 @@ impl OpenData {
         })
     }
 
+    /// Runs all checks on the data set. Errors are ordered by check, then FIR and entity, also
+    /// with the `parallel` feature.
     pub fn run_checks(&self) -> Result<(), Vec<Error>> {
-        let errs = self
-            .firs
-            .iter()
-            .filter_map(|(fir_name, fir)| {
-                info!("running volume checks for FIR {fir_name}");
-                fir.run_checks()
-                    .map_err(|errs| {
-                        errs.into_iter().map(|(vol, err)| {
-                            Error::InvalidVolume(fir_name.clone(), vol.clone(), err)
-                        })
-                    })
-                    .err()
-            })
+        let fir_errors = map_ordered(self.firs.keys().collect(), |fir_name| {
+            self.fir_checks(fir_name)
+        });
+
+        let errs = fir_errors
+            .into_iter()
             .flatten()
-            .chain(self.position_dupe_check().err().unwrap_or_default())
-            .chain(self.airport_dupe_check().err().unwrap_or_default())
-            .chain(self.position_ref_check().err().unwrap_or_default())
+            .chain(self.cross_fir_checks())
             .collect::<Vec<_>>();
         if errs.is_empty() {
             Ok(())
@@ -222,29 +476,177 @@ impl OpenData {
         }
     }
 
+    /// Checks only concerning the data of FIR `fir_name`, part of [`OpenData::run_checks`].
+    #[must_use]
+    pub fn fir_checks(&self, fir_name: &str) -> Vec<Error> {
+        let Some(fir) = self.firs.get(fir_name) else {
+            return vec![];
+        };
+        info!("running volume and runway checks for FIR {fir_name}");
+        let runway_errors = fir.airports.iter().flat_map(|(icao, airport)| {
+            airport
+                .check_runways()
+                .err()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|err| Error::InvalidRunway(fir_name.to_string(), icao.clone(), err))
+        });
+        fir.run_checks()
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(vol, err)| Error::InvalidVolume(fir_name.to_string(), vol.clone(), err))
+            .chain(runway_errors)
+            .collect()
+    }
+
+    /// Checks relating entities across FIRs, part of [`OpenData::run_checks`]. These need to be
+    /// rerun whenever any FIR changes.
+    #[must_use]
+    pub fn cross_fir_checks(&self) -> Vec<Error> {
+        let (position_errors, (airport_errors, ref_errors)) = join(
+            || self.position_dupe_check(),
+            || join(|| self.airport_dupe_check(), || self.position_ref_check()),
+        );
+        position_errors
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .chain(airport_errors.err().unwrap_or_default())
+            .chain(ref_errors.err().unwrap_or_default())
+            .chain(self.metadata_checks())
+            .chain(self.config_checks())
+            .collect()
+    }
+
+    /// UIRs and neighbours without FIR folder, and position references into other FIRs than the
+    /// declared neighbours, the UIR and its members of FIRs that declare neighbours in their
+    /// `fir.toml`
+    fn metadata_checks(&self) -> Vec<Error> {
+        let unknown_uir = self.firs.keys().filter_map(|fir_name| {
+            let uir = self.uir_of(fir_name)?;
+            (!self.firs.contains_key(uir))
+                .then(|| Error::UnknownUir(fir_name.clone(), uir.to_string()))
+        });
+        let unknown = self.firs.iter().flat_map(|(fir_name, fir)| {
+            fir.metadata
+                .iter()
+                .flat_map(|metadata| &metadata.neighbours)
+                .filter(|neighbour| !self.firs.contains_key(*neighbour))
+                .map(|neighbour| Error::UnknownNeighbour(fir_name.clone(), neighbour.clone()))
+        });
+
+        let references = self
+            .sectors()
+            .map(|(fir_name, id, sector)| {
+                let kind = InvalidPositionReferenceType::Sector;
+                (kind, fir_name, id, &sector.position_priority)
+            })
+            .chain(self.airports().map(|(fir_name, icao, airport)| {
+                let kind = InvalidPositionReferenceType::Airport;
+                (kind, fir_name, icao, &airport.position_priority)
+            }))
+            .sorted_by_key(|(_, fir_name, id, _)| (*fir_name, *id));
+        let non_neighbour = references.flat_map(|(kind, fir_name, id, priority)| {
+            let neighbours = self
+                .firs
+                .get(fir_name)
+                .and_then(|fir| fir.metadata.as_ref())
+                .map(|metadata| &metadata.neighbours)
+                .filter(|neighbours| !neighbours.is_empty());
+            priority.iter().flatten().filter_map(move |pos_ref| {
+                let fir = pos_ref.fir.as_ref()?;
+                let related = fir == fir_name
+                    || self.uir_of(fir_name) == Some(fir)
+                    || self.uir_of(fir) == Some(fir_name)
+                    || (self.uir_of(fir_name).is_some()
+                        && self.uir_of(fir_name) == self.uir_of(fir));
+                (!related && !neighbours?.contains(fir)).then(|| {
+                    Error::NonNeighbourReference(
+                        kind,
+                        fir_name.clone(),
+                        id.clone(),
+                        fir.clone(),
+                        pos_ref.id.clone(),
+                    )
+                })
+            })
+        });
+
+        unknown_uir.chain(unknown).chain(non_neighbour).collect()
+    }
+
+    /// Config entries for FIRs without folder, FIRs without config entry and `vateud8_ignore`
+    /// entries naming no position of their FIR
+    fn config_checks(&self) -> Vec<Error> {
+        let unknown = self
+            .config
+            .firs
+            .keys()
+            .filter(|fir_name| !self.firs.contains_key(*fir_name))
+            .map(|fir_name| Error::UnknownConfigFir(fir_name.clone()));
+        let missing = self
+            .firs
+            .keys()
+            .filter(|fir_name| !self.config.firs.contains_key(*fir_name))
+            .map(|fir_name| Error::MissingFirConfig(fir_name.clone()));
+        let unused = self
+            .config
+            .firs
+            .iter()
+            .filter_map(|(fir_name, fir_config)| {
+                Some((fir_name, fir_config, self.firs.get(fir_name)?))
+            })
+            .flat_map(|(fir_name, fir_config, fir)| {
+                fir_config
+                    .vateud8_ignore
+                    .iter()
+                    .filter(|position| !fir.positions.contains_key(*position))
+                    .map(|position| Error::UnusedVateud8Ignore(fir_name.clone(), position.clone()))
+            });
+        unknown.chain(missing).chain(unused).collect()
+    }
+
+    /// Positions that share frequency and station type with another position whose prefix is a
+    /// prefix of their own. Positions are bucketed by frequency and station type and indexed by
+    /// prefix, so only the prefixes of each position's prefix need to be looked up.
     fn position_dupe_check(&self) -> Result<(), Vec<Error>> {
         info!("running position duplicate checks");
         let positions = self
             .positions()
             .sorted_by_key(|(fir, pos_id, _)| (*fir, *pos_id))
             .collect::<Vec<_>>();
+
+        let mut index = HashMap::<_, HashMap<&str, Vec<usize>>>::new();
+        for (idx, (_, _, pos)) in positions.iter().enumerate() {
+            index
+                .entry((pos.frequency, &pos.station_type))
+                .or_default()
+                .entry(pos.prefix.as_str())
+                .or_default()
+                .push(idx);
+        }
+
         let errors = positions
             .iter()
-            .flat_map(|(fir, pos_id, pos)| {
-                positions
-                    .iter()
-                    .filter(move |(other_fir, other_pos_id, other_pos)| {
-                        (fir != other_fir || pos_id != other_pos_id)
-                            && pos.prefix.starts_with(&other_pos.prefix)
-                            && pos.frequency == other_pos.frequency
-                            && pos.station_type == other_pos.station_type
-                    })
-                    .map(|(other_fir, other_pos, _)| {
+            .enumerate()
+            .flat_map(|(idx, (fir, pos_id, pos))| {
+                let by_prefix = &index[&(pos.frequency, &pos.station_type)];
+                pos.prefix
+                    .char_indices()
+                    .map(|(end, _)| &pos.prefix[..end])
+                    .chain([pos.prefix.as_str()])
+                    .filter_map(|prefix| by_prefix.get(prefix))
+                    .flatten()
+                    .filter(move |other_idx| **other_idx != idx)
+                    .sorted()
+                    .map(|other_idx| {
+                        let (other_fir, other_pos, _) = positions[*other_idx];
                         Error::DuplicatePosition(
-                            (*fir).to_string(),
-                            (*pos_id).to_string(),
-                            (*other_fir).to_string(),
-                            (*other_pos).to_string(),
+                            (*fir).clone(),
+                            (*pos_id).clone(),
+                            other_fir.clone(),
+                            other_pos.clone(),
                         )
                     })
             })
@@ -257,40 +659,81 @@ impl OpenData {
         }
     }
 
+    /// Airports whose ICAO, IATA or fallback designators collide with another airport's. All
+    /// designators are indexed once, so each airport only looks up its own designators.
     fn airport_dupe_check(&self) -> Result<(), Vec<Error>> {
+        #[derive(PartialEq)]
+        enum Designator {
+            Icao,
+            Iata,
+            Fallback,
+        }
+
         info!("running airport duplicate checks");
         let airports = self
             .airports()
             .sorted_by_key(|(fir, airport, _)| (*fir, *airport))
             .collect::<Vec<_>>();
+
+        let mut index = HashMap::<&str, Vec<(usize, Designator)>>::new();
+        for (idx, (_, icao, airport)) in airports.iter().enumerate() {
+            index
+                .entry(icao.as_str())
+                .or_default()
+                .push((idx, Designator::Icao));
+            if let Some(iata) = &airport.iata_designator {
+                index
+                    .entry(iata.as_str())
+                    .or_default()
+                    .push((idx, Designator::Iata));
+            }
+            for prefix in &airport.fallback_prefixes {
+                index
+                    .entry(prefix.as_str())
+                    .or_default()
+                    .push((idx, Designator::Fallback));
+            }
+        }
+        let lookup = |designator: &str, matching: fn(&Designator) -> bool| {
+            index
+                .get(designator)
+                .into_iter()
+                .flatten()
+                .filter(move |(_, kind)| matching(kind))
+                .map(|(idx, _)| *idx)
+        };
+
         let errors = airports
             .iter()
-            .flat_map(|(fir, icao, airport)| {
-                airports
-                    .iter()
-                    .filter(move |(other_fir, other_icao, other_airport)| {
-                        (fir != other_fir || icao != other_icao)
-                            && (Some(*icao) == other_airport.iata_designator.as_ref()
-                                || icao == other_icao
-                                || other_airport.fallback_prefixes.contains(icao)
-                                || airport.iata_designator.as_ref().map_or(false, |iata| {
-                                    other_airport.fallback_prefixes.contains(iata)
-                                })
-                                || other_airport
-                                    .fallback_prefixes
-                                    .iter()
-                                    .any(|prefix| airport.fallback_prefixes.contains(prefix)))
-                    })
-                    .map(|(other_fir, other_icao, other_airport)| {
+            .enumerate()
+            .flat_map(|(idx, (fir, icao, airport))| {
+                // the ICAO code clashes with any designator of another airport, the IATA code and
+                // fallback prefixes only with other fallback prefixes
+                lookup(icao, |_| true)
+                    .chain(
+                        airport
+                            .iata_designator
+                            .iter()
+                            .chain(&airport.fallback_prefixes)
+                            .flat_map(|designator| {
+                                lookup(designator, |kind| *kind == Designator::Fallback)
+                            }),
+                    )
+                    .filter(|other_idx| *other_idx != idx)
+                    .sorted()
+                    .dedup()
+                    .map(|other_idx| {
+                        let (other_fir, other_icao, other_airport) = airports[other_idx];
                         Error::DuplicateAirport(
-                            (*fir).to_string(),
-                            (*icao).to_string(),
+                            (*fir).clone(),
+                            (*icao).clone(),
                             Box::new((*airport).clone()),
-                            (*other_fir).to_string(),
-                            (*other_icao).to_string(),
-                            Box::new((*other_airport).clone()),
+                            other_fir.clone(),
+                            other_icao.clone(),
+                            Box::new(other_airport.clone()),
                         )
                     })
+                    .collect::<Vec<_>>()
             })
             .collect::<Vec<_>>();
 
@@ -311,12 +754,7 @@ impl OpenData {
                     .position_priority
                     .iter()
                     .flatten()
-                    .filter(|pos_ref| {
-                        self.firs
-                            .get(pos_ref.fir.as_ref().unwrap_or(fir_name))
-                            .and_then(|fir| fir.positions.get(&pos_ref.id))
-                            .is_none()
-                    })
+                    .filter(|pos_ref| self.resolve(fir_name, pos_ref).is_none())
                     .map(|pos_ref| {
                         Error::InvalidPositionReference(
                             InvalidPositionReferenceType::Sector,
@@ -336,12 +774,7 @@ impl OpenData {
                     .position_priority
                     .iter()
                     .flatten()
-                    .filter(|pos_ref| {
-                        self.firs
-                            .get(pos_ref.fir.as_ref().unwrap_or(fir_name))
-                            .and_then(|fir| fir.positions.get(&pos_ref.id))
-                            .is_none()
-                    })
+                    .filter(|pos_ref| self.resolve(fir_name, pos_ref).is_none())
                     .map(|pos_ref| {
                         Error::InvalidPositionReference(
                             InvalidPositionReferenceType::Airport,
@@ -365,23 +798,25 @@ impl OpenData {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::collections::BTreeMap;
 
     use geo::point;
+    use itertools::Itertools;
 
     use crate::{
         position::{PositionReference, StationType},
-        Airport, Error, InvalidPositionReferenceType, OpenData, Position, Sector, FIR,
+        Airport, Config, Error, InvalidPositionReferenceType, OpenData, Position, Sector, Volume,
+        FIR,
     };
 
     #[test]
     fn test_pos_dupe() {
         let open_data = OpenData {
-            firs: HashMap::from([
+            firs: BTreeMap::from([
                 (
                     "TEST".to_string(),
                     FIR {
-                        positions: HashMap::from([(
+                        positions: BTreeMap::from([(
                             "POS1".to_string(),
                             Position {
                                 frequency: 134_150_000,
@@ -400,7 +835,7 @@ mod tests {
                 (
                     "AAAA".to_string(),
                     FIR {
-                        positions: HashMap::from([(
+                        positions: BTreeMap::from([(
                             "POS2".to_string(),
                             Position {
                                 frequency: 134_150_000,
@@ -419,7 +854,7 @@ mod tests {
                 (
                     "EDMM".to_string(),
                     FIR {
-                        positions: HashMap::from([
+                        positions: BTreeMap::from([
                             (
                                 "DMSD".to_string(),
                                 Position {
@@ -486,11 +921,11 @@ mod tests {
     #[test]
     fn test_airport_dupe() {
         let open_data = OpenData {
-            firs: HashMap::from([
+            firs: BTreeMap::from([
                 (
                     "TEST".to_string(),
                     FIR {
-                        airports: HashMap::from([(
+                        airports: BTreeMap::from([(
                             "LIPB".to_string(),
                             Airport {
                                 name: "Bolzano".to_string(),
@@ -508,7 +943,7 @@ mod tests {
                 (
                     "AAAA".to_string(),
                     FIR {
-                        airports: HashMap::from([
+                        airports: BTreeMap::from([
                             (
                                 "BLZ".to_string(),
                                 Airport {
@@ -615,11 +1050,11 @@ mod tests {
     #[test]
     fn test_pos_ref() {
         let open_data = OpenData {
-            firs: HashMap::from([
+            firs: BTreeMap::from([
                 (
                     "TEST".to_string(),
                     FIR {
-                        positions: HashMap::from([(
+                        positions: BTreeMap::from([(
                             "POS1".to_string(),
                             Position {
                                 frequency: 134_150_000,
@@ -632,7 +1067,7 @@ mod tests {
                                 gcap_tier: None,
                             },
                         )]),
-                        airports: HashMap::from([(
+                        airports: BTreeMap::from([(
                             "CHEK".to_string(),
                             Airport {
                                 name: "Check Airport".to_string(),
@@ -657,7 +1092,7 @@ mod tests {
                                 runways: vec![],
                             },
                         )]),
-                        sectors: HashMap::from([(
+                        sectors: BTreeMap::from([(
                             "SEC1".to_string(),
                             Sector {
                                 name: None,
@@ -685,7 +1120,7 @@ mod tests {
                 (
                     "AAAA".to_string(),
                     FIR {
-                        airports: HashMap::from([(
+                        airports: BTreeMap::from([(
                             "ABCD".to_string(),
                             Airport {
                                 name: "Alphabet Airport".to_string(),
@@ -710,7 +1145,7 @@ mod tests {
                                 runways: vec![],
                             },
                         )]),
-                        sectors: HashMap::from([(
+                        sectors: BTreeMap::from([(
                             "ABC".to_string(),
                             Sector {
                                 name: None,
@@ -838,4 +1273,323 @@ mod tests {
             _ => unreachable!("must be invalid position reference"),
         }
     }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_folder_roundtrip() {
+        let fir = euroscope_fir();
+        let files = fir.to_files().unwrap();
+        let from_files =
+            FIR::from_files(files.iter().map(|(name, c)| (*name, c.as_str()))).unwrap();
+        assert_eq!(from_files.to_files().unwrap(), files);
+
+        let dir = std::env::temp_dir().join(format!("vatsim-open-data-{}", std::process::id()));
+        fir.to_folder(&dir).unwrap();
+        let read = FIR::from_folder(&dir);
+        let strict = FIR::try_from_folder(&dir).map(|fir| fir.to_files().unwrap());
+        std::fs::write(dir.join("positions.toml"), "[MMR\n").unwrap();
+        let broken = FIR::try_from_folder(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(strict.unwrap(), files);
+        assert!(matches!(broken, Err(Error::TomlDeserialize(_))));
+
+        assert_eq!(read.positions["MMR"].frequency, 129_100_000);
+        assert_eq!(read.sectors["TEST"].position_priority[0][0].id, "MMR");
+        assert_eq!(read.volumes["TEST"].upper_level(), 245);
+        assert_eq!(
+            read.airports["EDDM"].location,
+            fir.airports["EDDM"].location
+        );
+    }
+
+    #[test]
+    fn test_check_order() {
+        // FIR checks run in parallel with the `parallel` feature, the result must not depend on
+        // which FIR finishes first
+        let mut open_data = colliding_world();
+        for (fir_idx, fir) in open_data.firs.values_mut().enumerate() {
+            let bounds = euroscope_fir().volumes["TEST"].lateral_bounds().clone();
+            for (lower, upper) in [(300, 200), (100, 1000), (0, 245)] {
+                let upper = upper + u64::try_from(fir_idx).unwrap();
+                fir.volumes.insert(
+                    format!("V{lower}"),
+                    Volume::new(lower, upper, bounds.clone()),
+                );
+            }
+        }
+        let sequential = open_data
+            .firs
+            .keys()
+            .flat_map(|fir_name| open_data.fir_checks(fir_name))
+            .chain(open_data.cross_fir_checks())
+            .map(|e| e.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sequential
+                .iter()
+                .filter(|e| e.starts_with("Invalid volumes"))
+                .count(),
+            16
+        );
+
+        for _ in 0..10 {
+            let errors = open_data.run_checks().unwrap_err();
+            assert_eq!(
+                errors.iter().map(ToString::to_string).collect::<Vec<_>>(),
+                sequential
+            );
+        }
+    }
+
+    #[test]
+    fn test_dupe_checks_match_pairwise() {
+        let open_data = colliding_world();
+        let positions = open_data
+            .positions()
+            .sorted_by_key(|(fir, pos_id, _)| (*fir, *pos_id))
+            .collect::<Vec<_>>();
+        let expected_positions = positions
+            .iter()
+            .flat_map(|(fir, pos_id, pos)| {
+                positions
+                    .iter()
+                    .filter(move |(other_fir, other_pos_id, other_pos)| {
+                        (fir != other_fir || pos_id != other_pos_id)
+                            && pos.prefix.starts_with(&other_pos.prefix)
+                            && pos.frequency == other_pos.frequency
+                            && pos.station_type == other_pos.station_type
+                    })
+                    .map(move |(other_fir, other_pos_id, _)| {
+                        format!("{fir}-{pos_id}-{other_fir}-{other_pos_id}")
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let airports = open_data
+            .airports()
+            .sorted_by_key(|(fir, icao, _)| (*fir, *icao))
+            .collect::<Vec<_>>();
+        let expected_airports = airports
+            .iter()
+            .flat_map(|(fir, icao, airport)| {
+                airports
+                    .iter()
+                    .filter(move |(other_fir, other_icao, other_airport)| {
+                        (fir != other_fir || icao != other_icao)
+                            && (Some(*icao) == other_airport.iata_designator.as_ref()
+                                || icao == other_icao
+                                || other_airport.fallback_prefixes.contains(icao)
+                                || airport.iata_designator.as_ref().is_some_and(|iata| {
+                                    other_airport.fallback_prefixes.contains(iata)
+                                })
+                                || other_airport
+                                    .fallback_prefixes
+                                    .iter()
+                                    .any(|prefix| airport.fallback_prefixes.contains(prefix)))
+                    })
+                    .map(move |(other_fir, other_icao, _)| {
+                        format!("{fir}-{icao}-{other_fir}-{other_icao}")
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let found_positions = open_data
+            .position_dupe_check()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| match e {
+                Error::DuplicatePosition(fir, pos_id, other_fir, other_pos_id) => {
+                    format!("{fir}-{pos_id}-{other_fir}-{other_pos_id}")
+                }
+                _ => panic!("unexpected error {e}"),
+            })
+            .collect::<Vec<_>>();
+        let found_airports = open_data
+            .airport_dupe_check()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| match e {
+                Error::DuplicateAirport(fir, icao, _, other_fir, other_icao, _) => {
+                    format!("{fir}-{icao}-{other_fir}-{other_icao}")
+                }
+                _ => panic!("unexpected error {e}"),
+            })
+            .collect::<Vec<_>>();
+
+        assert!(expected_positions.len() > 100);
+        assert_eq!(found_positions, expected_positions);
+        assert!(expected_airports.len() > 10);
+        assert_eq!(found_airports, expected_airports);
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let mut open_data = OpenData::default();
+        open_data.firs.insert("EDMM".to_string(), euroscope_fir());
+        let mut edgg = euroscope_fir();
+        edgg.airports.clear();
+        open_data.firs.insert("EDGG".to_string(), edgg);
+
+        insta::assert_json_snapshot!("open_data", open_data);
+        insta::assert_snapshot!(
+            "check_errors",
+            open_data
+                .run_checks()
+                .unwrap_err()
+                .iter()
+                .map(ToString::to_string)
+                .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_config_checks() {
+        assert!(Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_regoin = 8\n").is_err());
+
+        let mut open_data = OpenData {
+            config: Config::from_toml_str(
+                "[vateud8]\n[firs.EDMM]\nvateud8_ignore = [\"MMR\", \"GONE\"]\n[firs.EDXX]\n",
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+        open_data.firs.insert("EDMM".to_string(), euroscope_fir());
+        open_data.firs.insert("EDGG".to_string(), FIR::default());
+        assert_eq!(
+            open_data
+                .config_checks()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "Config for unknown FIR EDXX",
+                "FIR EDGG has no config entry",
+                "vateud8_ignore of FIR EDMM lists unknown position GONE",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_neighbour_check() {
+        let mut edmm = euroscope_fir();
+        edmm.set_file(
+            "fir.toml",
+            "name = \"München\"\nneighbours = [\"EDGG\", \"LOVV\"]\n",
+        )
+        .unwrap();
+        edmm.sectors.get_mut("TEST").unwrap().position_priority = vec![vec![
+            PositionReference {
+                fir: Some("EDGG".to_string()),
+                id: "MMR".to_string(),
+            },
+            PositionReference {
+                fir: Some("LKAA".to_string()),
+                id: "PRG".to_string(),
+            },
+        ]];
+        let files = edmm.to_files().unwrap();
+        assert_eq!(files[0].0, "fir.toml");
+        let edmm = FIR::from_files(
+            files
+                .iter()
+                .map(|(name, contents)| (*name, contents.as_str())),
+        )
+        .unwrap();
+        assert_eq!(
+            edmm.metadata.as_ref().unwrap().name.as_deref(),
+            Some("München")
+        );
+
+        let open_data = OpenData {
+            firs: BTreeMap::from([
+                ("EDMM".to_string(), edmm),
+                ("EDGG".to_string(), FIR::default()),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(
+            open_data
+                .metadata_checks()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "FIR EDMM declares unknown neighbour LOVV",
+                "Position reference to non-neighbouring FIR: LKAA-PRG (in Sector EDMM-TEST)",
+            ]
+        );
+    }
+
+    pub(crate) fn euroscope_fir() -> FIR {
+        crate::euroscope::import_str(
+            "[POSITIONS]
+Muenchen Radar:München Radar:129.100:MMR:R:EDMM:CTR:-:-:2201:2277
+[AIRSPACE]
+SECTORLINE:BORDER
+COORD:N048.00.00.000:E011.00.00.000
+COORD:N048.00.00.000:E012.00.00.000
+COORD:N049.00.00.000:E012.00.00.000
+SECTOR:TEST:0:24500
+OWNER:MMR
+BORDER:BORDER
+",
+            Some("[AIRPORT]\nEDDM 118.705 N048.21.13.000 E011.47.10.000 D\n"),
+            crate::DEFAULT_ARC_RESOLUTION,
+        )
+        .unwrap()
+    }
+
+    /// Small designator and frequency spaces to provoke many collisions
+    fn colliding_world() -> OpenData {
+        let mut seed = 42;
+        let mut open_data = OpenData::default();
+        for fir_idx in 0..8 {
+            let fir = open_data.firs.entry(format!("FIR{fir_idx}")).or_default();
+            for pos_idx in 0..40 {
+                fir.positions.insert(
+                    format!("P{pos_idx}"),
+                    Position {
+                        frequency: 118_000_000 + u32::try_from(pos_idx % 5).unwrap() * 5_000,
+                        prefix: designator(&mut seed, 1 + pos_idx % 4),
+                        station_type: [StationType::Center, StationType::Approach][pos_idx % 2]
+                            .clone(),
+                        radio_callsign: String::new(),
+                        name: None,
+                        cpdlc_logon: None,
+                        airspace_groups: vec![],
+                        gcap_tier: None,
+                    },
+                );
+            }
+            for _ in 0..20 {
+                let airport = Airport {
+                    name: String::new(),
+                    iata_designator: (random(&mut seed, 2) == 0).then(|| designator(&mut seed, 3)),
+                    fallback_prefixes: (0..random(&mut seed, 3))
+                        .map(|_| designator(&mut seed, 3))
+                        .collect(),
+                    location: point!(x: 0.0, y: 0.0),
+                    elevation: None,
+                    position_priority: vec![],
+                    runways: vec![],
+                };
+                fir.airports.insert(designator(&mut seed, 4), airport);
+            }
+        }
+        open_data
+    }
+
+    fn random(seed: &mut u64, n: usize) -> usize {
+        *seed = seed
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        usize::try_from(*seed >> 33).unwrap() % n
+    }
+
+    fn designator(seed: &mut u64, len: usize) -> String {
+        (0..len)
+            .map(|_| char::from(b"ABC"[random(seed, 3)]))
+            .collect()
+    }
 }
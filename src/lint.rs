@@ -0,0 +1,308 @@
+//! Severity overrides and suppressions of check results, configured in `config.toml`:
+//!
+//! ```toml
+//! [lints.rules]
+//! vateud8-superfluous = "warning"
+//!
+//! [[firs.EDMM.lints.suppress]]
+//! rule = "duplicate-position"
+//! entity = "DMSD"
+//! reason = "shared frequency coordinated with EDDM tower"
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diagnostic::{self, Diagnostic, Severity},
+    Config, FirName,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn severity(self) -> Option<Severity> {
+        match self {
+            Self::Off => None,
+            Self::Info => Some(Severity::Info),
+            Self::Warning => Some(Severity::Warning),
+            Self::Error => Some(Severity::Error),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    /// Level per rule id, overriding the default severity
+    #[serde(default)]
+    rules: BTreeMap<String, Level>,
+    #[serde(default)]
+    suppress: Vec<Suppression>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suppression {
+    rule: String,
+    /// Only in the global config, FIR the entity belongs to, any FIR if not given
+    fir: Option<FirName>,
+    /// Id of the position, sector, airport or volume
+    entity: String,
+    reason: String,
+}
+
+/// Applies the lint configuration to `diagnostics`: drops suppressed findings and rules turned
+/// off, adjusts severities and reports unknown rule ids and suppressions that are invalid or did
+/// not match anything.
+/// `checked(rule, fir)` tells whether a rule ran for a FIR (or all FIRs for `None`), suppressions
+/// of rules that did not run are never stale.
+pub fn apply(
+    config: &Config,
+    diagnostics: Vec<Diagnostic>,
+    checked: impl Fn(&str, Option<&str>) -> bool,
+) -> Vec<Diagnostic> {
+    let suppressions = config
+        .lints
+        .suppress
+        .iter()
+        .map(|suppression| (None, suppression))
+        .chain(config.firs.iter().flat_map(|(fir_name, fir_config)| {
+            fir_config
+                .lints
+                .suppress
+                .iter()
+                .map(move |suppression| (Some(fir_name), suppression))
+        }))
+        .collect::<Vec<_>>();
+    let mut used = vec![false; suppressions.len()];
+
+    let mut result = diagnostics
+        .into_iter()
+        .filter_map(|mut diagnostic| {
+            let suppressed = suppressions.iter().position(|(fir, suppression)| {
+                invalid(*fir, suppression).is_none()
+                    && suppression.rule == diagnostic.rule
+                    && fir
+                        .or(suppression.fir.as_ref())
+                        .is_none_or(|fir| diagnostic.fir.as_ref() == Some(fir))
+                    && diagnostic.entity.as_ref() == Some(&suppression.entity)
+            });
+            if let Some(idx) = suppressed {
+                used[idx] = true;
+                return None;
+            }
+
+            if let Some(level) = level(config, diagnostic.fir.as_deref(), diagnostic.rule) {
+                diagnostic.severity = level.severity()?;
+            }
+            Some(diagnostic)
+        })
+        .collect::<Vec<_>>();
+
+    let unknown_rules = unknown_rules(config).map(|(fir, rule)| {
+        let message = format!("Level set for unknown rule {rule}");
+        ("config-unknown-rule", fir, rule, message)
+    });
+    let invalid_suppressions =
+        suppressions
+            .iter()
+            .zip(used)
+            .filter_map(|((fir, suppression), used)| {
+                let (rule, message) = if diagnostic::rule(&suppression.rule).is_none() {
+                    (
+                        "config-unknown-rule",
+                        format!(
+                            "Suppression of unknown rule {} for {}",
+                            suppression.rule, suppression.entity
+                        ),
+                    )
+                } else if let Some(problem) = invalid(*fir, suppression) {
+                    (
+                        "invalid-suppression",
+                        format!(
+                            "Suppression of {} for {} {problem}",
+                            suppression.rule, suppression.entity
+                        ),
+                    )
+                } else if !used
+                    && checked(
+                        &suppression.rule,
+                        fir.or(suppression.fir.as_ref()).map(String::as_str),
+                    )
+                {
+                    (
+                        "stale-suppression",
+                        format!(
+                            "Suppression of {} for {} does not match any finding",
+                            suppression.rule, suppression.entity
+                        ),
+                    )
+                } else {
+                    return None;
+                };
+                Some((
+                    rule,
+                    fir.or(suppression.fir.as_ref()),
+                    &suppression.entity,
+                    message,
+                ))
+            });
+    for (rule, fir, entity, message) in unknown_rules.chain(invalid_suppressions) {
+        let mut diagnostic = Diagnostic::new(rule, message);
+        diagnostic.fir = fir.cloned();
+        diagnostic.entity = Some(entity.clone());
+        diagnostic.file = Some("config.toml".into());
+        if let Some(level) = level(config, fir.map(String::as_str), rule) {
+            let Some(severity) = level.severity() else {
+                continue;
+            };
+            diagnostic.severity = severity;
+        }
+        result.push(diagnostic);
+    }
+
+    result
+}
+
+/// Level configured for `rule`, in the config of `fir` or else globally
+fn level<'a>(config: &'a Config, fir: Option<&str>, rule: &str) -> Option<&'a Level> {
+    fir.and_then(|fir| config.firs.get(fir))
+        .and_then(|fir_config| fir_config.lints.rules.get(rule))
+        .or_else(|| config.lints.rules.get(rule))
+}
+
+/// Why a suppression in the config of `fir`, `None` for the global config, cannot be applied
+fn invalid(fir: Option<&FirName>, suppression: &Suppression) -> Option<&'static str> {
+    if suppression.reason.trim().is_empty() {
+        Some("has no reason")
+    } else if fir.is_some() && suppression.fir.is_some() {
+        Some("sets fir, which is only allowed in the global config")
+    } else {
+        None
+    }
+}
+
+/// Rule ids in `[lints.rules]` of the global or a FIR config that name no rule
+fn unknown_rules(config: &Config) -> impl Iterator<Item = (Option<&FirName>, &String)> {
+    config
+        .lints
+        .rules
+        .keys()
+        .map(|rule| (None, rule))
+        .chain(config.firs.iter().flat_map(|(fir_name, fir_config)| {
+            fir_config
+                .lints
+                .rules
+                .keys()
+                .map(move |rule| (Some(fir_name), rule))
+        }))
+        .filter(|(_, rule)| diagnostic::rule(rule).is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        diagnostic::{Diagnostic, Severity},
+        Config, Error,
+    };
+
+    #[test]
+    fn test_apply() {
+        let config = toml::from_str::<Config>(
+            r#"
+            [vateud8]
+
+            [lints.rules]
+            invalid-volume = "off"
+            duplicate-positions = "off"
+
+            [[lints.suppress]]
+            rule = "duplicate-airport"
+            entity = "EDDM"
+            reason = "never fires"
+
+            [firs.EDMM]
+            lints.rules.duplicate-position = "warning"
+            lints.rules.stale-suppression = "info"
+            lints.rules.invalid-suppression = "warning"
+
+            [[firs.EDMM.lints.suppress]]
+            rule = "duplicate-position"
+            entity = "DMSD"
+            reason = "coordinated"
+
+            [[firs.EDMM.lints.suppress]]
+            rule = "duplicate-position"
+            entity = "DMSE"
+            reason = " "
+
+            [[firs.EDMM.lints.suppress]]
+            rule = "duplicate-postion"
+            entity = "DMSF"
+            reason = "typo"
+
+            [[firs.EDMM.lints.suppress]]
+            rule = "duplicate-position"
+            fir = "EDGG"
+            entity = "DMSD"
+            reason = "only allowed globally"
+
+            [[firs.EDMM.lints.suppress]]
+            rule = "duplicate-airport"
+            entity = "EDDN"
+            reason = "never fires either"
+            "#,
+        )
+        .unwrap();
+
+        let duplicate = |fir: &str, id: &str| {
+            Diagnostic::from(&Error::DuplicatePosition(
+                fir.to_string(),
+                id.to_string(),
+                "EDGG".to_string(),
+                "GIN".to_string(),
+            ))
+        };
+        let diagnostics = vec![
+            duplicate("EDMM", "DMSD"),
+            duplicate("EDMM", "DMSE"),
+            duplicate("EDGG", "DMSD"),
+            Diagnostic::new("invalid-volume", String::new()),
+        ];
+
+        let result = super::apply(&config, diagnostics.clone(), |_, _| true);
+        let summary = result
+            .iter()
+            .map(|d| (d.rule, d.severity, d.entity.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                ("duplicate-position", Severity::Warning, Some("DMSE")),
+                ("duplicate-position", Severity::Error, Some("DMSD")),
+                (
+                    "config-unknown-rule",
+                    Severity::Error,
+                    Some("duplicate-positions")
+                ),
+                ("stale-suppression", Severity::Warning, Some("EDDM")),
+                ("invalid-suppression", Severity::Warning, Some("DMSE")),
+                ("config-unknown-rule", Severity::Error, Some("DMSF")),
+                ("invalid-suppression", Severity::Warning, Some("DMSD")),
+                ("stale-suppression", Severity::Info, Some("EDDN")),
+            ]
+        );
+
+        let result = super::apply(&config, diagnostics, |rule, _| rule != "duplicate-airport");
+        assert!(result.iter().all(|d| d.rule != "stale-suppression"));
+    }
+}
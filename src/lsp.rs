@@ -0,0 +1,782 @@
+//! Language server for the TOML data files, offering check results as diagnostics, go to
+//! definition of position and volume references, completion of ids and hover information on
+//! positions.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error as StdError,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use lsp_server::{Connection, Message, Notification, Request, Response};
+use lsp_types::{
+    self as lsp,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{Completion, GotoDefinition, HoverRequest, Request as LspRequest},
+};
+use tracing::{info, warn};
+
+use crate::{
+    diagnostic::{self, Diagnostic, Severity},
+    lint, Config, Error, OpenData, Position, StationType,
+};
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// Runs the server on `connection` until the client shuts it down. The data set is read from the
+/// first workspace folder.
+pub fn serve(connection: &Connection) -> Result<(), BoxError> {
+    let capabilities = lsp::ServerCapabilities {
+        text_document_sync: Some(lsp::TextDocumentSyncCapability::Kind(
+            lsp::TextDocumentSyncKind::FULL,
+        )),
+        definition_provider: Some(lsp::OneOf::Left(true)),
+        hover_provider: Some(lsp::HoverProviderCapability::Simple(true)),
+        completion_provider: Some(lsp::CompletionOptions {
+            trigger_characters: Some(vec!["\"".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let params = serde_json::from_value::<lsp::InitializeParams>(
+        connection.initialize(serde_json::to_value(capabilities)?)?,
+    )?;
+    #[allow(deprecated, reason = "fallback for clients without workspace folders")]
+    let root = params
+        .workspace_folders
+        .and_then(|folders| folders.into_iter().next())
+        .map(|folder| folder.uri)
+        .or(params.root_uri)
+        .map(|uri| uri_to_path(&uri))
+        .ok_or("no workspace folder")?;
+    info!("serving open data in {}", root.display());
+
+    let (open_data, errors) = OpenData::from_path_with_errors(&root)?;
+    let mut server = Server {
+        open_data,
+        root,
+        documents: HashMap::new(),
+        parse_errors: HashMap::new(),
+        published: BTreeSet::new(),
+    };
+    // reloading keeps the entities of the unreadable files empty and records the parse errors
+    for e in errors {
+        if let Error::InvalidDataFile(fir_name, file, _) = e {
+            let path = server.root.join("FIRs").join(fir_name).join(file);
+            server.reload(&path);
+        }
+    }
+    server.publish_diagnostics(connection)?;
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = server.handle_request(request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if server.handle_notification(notification) {
+                    server.publish_diagnostics(connection)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// The data files a document can be
+#[derive(Clone, Copy, PartialEq)]
+enum FileKind {
+    Positions,
+    Sectors,
+    Airports,
+    Volumes,
+    Metadata,
+}
+
+impl FileKind {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Positions => "positions.toml",
+            Self::Sectors => "sectors.toml",
+            Self::Airports => "airports.toml",
+            Self::Volumes => "volumes.geojson",
+            Self::Metadata => "fir.toml",
+        }
+    }
+}
+
+struct Server {
+    root: PathBuf,
+    open_data: OpenData,
+    /// Contents of documents open in the editor, which may differ from the files on disk
+    documents: HashMap<PathBuf, String>,
+    parse_errors: HashMap<PathBuf, lsp::Diagnostic>,
+    /// Files diagnostics were published for, to clear them once fixed
+    published: BTreeSet<PathBuf>,
+}
+
+impl Server {
+    /// FIR name and kind of a data file
+    fn classify(&self, path: &Path) -> Option<(String, FileKind)> {
+        let relative = path.strip_prefix(self.root.join("FIRs")).ok()?;
+        let mut components = relative.iter();
+        let fir_name = components.next()?.to_string_lossy().to_string();
+        let kind = match components.next()?.to_str()? {
+            "positions.toml" => FileKind::Positions,
+            "sectors.toml" => FileKind::Sectors,
+            "airports.toml" => FileKind::Airports,
+            "volumes.geojson" => FileKind::Volumes,
+            "fir.toml" => FileKind::Metadata,
+            _ => return None,
+        };
+        components.next().is_none().then_some((fir_name, kind))
+    }
+
+    fn contents(&self, path: &Path) -> Option<String> {
+        self.documents
+            .get(path)
+            .cloned()
+            .or_else(|| read_to_string(path).ok())
+    }
+
+    /// Replaces the data of a file or the config in the data set by the current contents,
+    /// returns whether diagnostics need to be republished.
+    fn reload(&mut self, path: &Path) -> bool {
+        let contents = self.contents(path);
+        let result = if path == self.root.join("config.toml") {
+            // a deleted config keeps the last one read
+            let Some(contents) = &contents else {
+                return false;
+            };
+            Config::from_toml_str(contents).map(|config| self.open_data.config = config)
+        } else {
+            let Some((fir_name, kind)) = self.classify(path) else {
+                return false;
+            };
+            let fir = self.open_data.firs.entry(fir_name).or_default();
+            if let Some(contents) = &contents {
+                fir.set_file(kind.file_name(), contents)
+            } else {
+                match kind {
+                    FileKind::Positions => fir.positions.clear(),
+                    FileKind::Sectors => fir.sectors.clear(),
+                    FileKind::Airports => fir.airports.clear(),
+                    FileKind::Volumes => fir.volumes.clear(),
+                    FileKind::Metadata => fir.metadata = None,
+                }
+                Ok(())
+            }
+        };
+        let contents = contents.unwrap_or_default();
+        match result {
+            Ok(()) => {
+                self.parse_errors.remove(path);
+            }
+            Err(e) => {
+                let span = match &e {
+                    Error::TomlDeserialize(e) => e.span(),
+                    _ => None,
+                };
+                let start = span.as_ref().map_or(0, |span| span.start);
+                let end = span.map_or(0, |span| span.end);
+                self.parse_errors.insert(
+                    path.to_path_buf(),
+                    lsp::Diagnostic {
+                        range: lsp::Range::new(
+                            position_at(&contents, start),
+                            position_at(&contents, end),
+                        ),
+                        severity: Some(lsp::DiagnosticSeverity::ERROR),
+                        code: Some(lsp::NumberOrString::String("read-error".to_string())),
+                        message: match &e {
+                            Error::TomlDeserialize(e) => e.message().to_string(),
+                            e => e.to_string(),
+                        },
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        true
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> bool {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = extract::<DidOpenTextDocument>(notification) else {
+                    return false;
+                };
+                let path = uri_to_path(&params.text_document.uri);
+                self.documents
+                    .insert(path.clone(), params.text_document.text);
+                self.reload(&path)
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(mut params) = extract::<DidChangeTextDocument>(notification) else {
+                    return false;
+                };
+                // full sync, the last change holds the whole document
+                let Some(change) = params.content_changes.pop() else {
+                    return false;
+                };
+                let path = uri_to_path(&params.text_document.uri);
+                self.documents.insert(path.clone(), change.text);
+                self.reload(&path)
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = extract::<DidCloseTextDocument>(notification) else {
+                    return false;
+                };
+                let path = uri_to_path(&params.text_document.uri);
+                self.documents.remove(&path);
+                self.reload(&path)
+            }
+            _ => false,
+        }
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => {
+                respond::<GotoDefinition>(request, |params| self.definition(&params))
+            }
+            HoverRequest::METHOD => respond::<HoverRequest>(request, |params| self.hover(&params)),
+            Completion::METHOD => respond::<Completion>(request, |params| self.completion(&params)),
+            _ => {
+                return Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request {}", request.method),
+                )
+            }
+        };
+        match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(e) => Response::new_err(
+                id,
+                lsp_server::ErrorCode::InvalidParams as i32,
+                e.to_string(),
+            ),
+        }
+    }
+
+    fn publish_diagnostics(&mut self, connection: &Connection) -> Result<(), BoxError> {
+        let errors = self.open_data.run_checks().err().unwrap_or_default();
+        let diagnostics = lint::apply(
+            &self.open_data.config,
+            errors.iter().map(Diagnostic::from).collect(),
+            |_, _| true,
+        );
+
+        let mut by_file = BTreeMap::<PathBuf, Vec<lsp::Diagnostic>>::new();
+        for diagnostic in diagnostics {
+            let Some(file) = &diagnostic.file else {
+                continue;
+            };
+            let path = self.root.join(file);
+            let contents = self.contents(&path).unwrap_or_default();
+            let line = diagnostic
+                .entity
+                .as_ref()
+                .and_then(|entity| diagnostic::find_definition(&contents, entity))
+                .unwrap_or_default();
+            by_file.entry(path).or_default().push(lsp::Diagnostic {
+                range: line_range(&contents, line),
+                severity: Some(match diagnostic.severity {
+                    Severity::Error => lsp::DiagnosticSeverity::ERROR,
+                    Severity::Warning => lsp::DiagnosticSeverity::WARNING,
+                    Severity::Info => lsp::DiagnosticSeverity::INFORMATION,
+                }),
+                code: Some(lsp::NumberOrString::String(diagnostic.rule.to_string())),
+                source: Some(env!("CARGO_PKG_NAME").to_string()),
+                message: diagnostic.message,
+                ..Default::default()
+            });
+        }
+        for (path, parse_error) in &self.parse_errors {
+            by_file
+                .entry(path.clone())
+                .or_default()
+                .push(parse_error.clone());
+        }
+
+        let files = by_file.keys().cloned().collect::<BTreeSet<_>>();
+        for path in self.published.difference(&files) {
+            by_file.entry(path.clone()).or_default();
+        }
+        for (path, diagnostics) in by_file {
+            let params = lsp::PublishDiagnosticsParams::new(path_to_uri(&path), diagnostics, None);
+            connection
+                .sender
+                .send(Message::Notification(Notification::new(
+                    PublishDiagnostics::METHOD.to_string(),
+                    params,
+                )))?;
+        }
+        self.published = files;
+        Ok(())
+    }
+
+    /// Document, FIR and reference context at a position in a document
+    fn context(&self, params: &lsp::TextDocumentPositionParams) -> Option<Cursor> {
+        let path = uri_to_path(&params.text_document.uri);
+        let (fir_name, kind) = self.classify(&path)?;
+        let contents = self.contents(&path)?;
+        let offset = offset_at(&contents, params.position);
+        let (start, end) = token_at(&contents, offset);
+        let token = contents[start..end].to_string();
+        let key = key_before(&contents, start);
+        let ref_fir = (key.as_deref() == Some("id"))
+            .then(|| inline_table_fir(&contents, offset))
+            .flatten()
+            .unwrap_or_else(|| fir_name.clone());
+        Some(Cursor {
+            fir_name,
+            kind,
+            token,
+            key,
+            ref_fir,
+        })
+    }
+
+    /// The position a cursor is on, either referenced or defined
+    fn position(&self, cursor: &Cursor) -> Option<(&str, &Position)> {
+        let fir = self.open_data.firs.get(&cursor.ref_fir)?;
+        let is_reference =
+            cursor.kind != FileKind::Positions && cursor.key.as_deref() == Some("id");
+        let is_definition = cursor.kind == FileKind::Positions && cursor.key.is_none();
+        (is_reference || is_definition)
+            .then(|| fir.positions.get_key_value(&cursor.token))
+            .flatten()
+            .map(|(id, position)| (id.as_str(), position))
+    }
+
+    fn definition(
+        &self,
+        params: &lsp::GotoDefinitionParams,
+    ) -> Option<lsp::GotoDefinitionResponse> {
+        let cursor = self.context(&params.text_document_position_params)?;
+        let (fir_name, file) = match (cursor.kind, cursor.key.as_deref()) {
+            (FileKind::Sectors | FileKind::Airports, Some("id")) => {
+                (&cursor.ref_fir, "positions.toml")
+            }
+            (FileKind::Sectors, Some("volumes")) => (&cursor.fir_name, "volumes.geojson"),
+            _ => return None,
+        };
+        let path = self.root.join("FIRs").join(fir_name).join(file);
+        let contents = self.contents(&path)?;
+        let line = diagnostic::find_definition(&contents, &cursor.token)?;
+        Some(lsp::GotoDefinitionResponse::Scalar(lsp::Location::new(
+            path_to_uri(&path),
+            line_range(&contents, line),
+        )))
+    }
+
+    fn hover(&self, params: &lsp::HoverParams) -> Option<lsp::Hover> {
+        let cursor = self.context(&params.text_document_position_params)?;
+        let (id, position) = self.position(&cursor)?;
+        let mut text = format!(
+            "**{}** {} ({}/{id})\n\n{}.{:03} MHz",
+            position.callsign(),
+            position.radio_callsign,
+            cursor.ref_fir,
+            position.frequency / 1_000_000,
+            position.frequency % 1_000_000 / 1_000,
+        );
+        if let Some(name) = &position.name {
+            text = format!("{text}\n\n{name}");
+        }
+        Some(lsp::Hover {
+            contents: lsp::HoverContents::Markup(lsp::MarkupContent {
+                kind: lsp::MarkupKind::Markdown,
+                value: text,
+            }),
+            range: None,
+        })
+    }
+
+    fn completion(&self, params: &lsp::CompletionParams) -> Option<lsp::CompletionResponse> {
+        let cursor = self.context(&params.text_document_position)?;
+        let item = |label: &str, detail: Option<String>, kind| lsp::CompletionItem {
+            label: label.to_string(),
+            detail,
+            kind: Some(kind),
+            ..Default::default()
+        };
+        let items = match cursor.key.as_deref()? {
+            "id" => self
+                .open_data
+                .firs
+                .get(&cursor.ref_fir)?
+                .positions
+                .iter()
+                .map(|(id, position)| {
+                    item(
+                        id,
+                        Some(position.callsign()),
+                        lsp::CompletionItemKind::REFERENCE,
+                    )
+                })
+                .collect(),
+            "fir" => self
+                .open_data
+                .firs
+                .keys()
+                .map(|fir_name| item(fir_name, None, lsp::CompletionItemKind::MODULE))
+                .collect(),
+            "volumes" => self
+                .open_data
+                .firs
+                .get(&cursor.fir_name)?
+                .volumes
+                .keys()
+                .map(|id| item(id, None, lsp::CompletionItemKind::REFERENCE))
+                .collect(),
+            "station_type" => StationType::ALL
+                .iter()
+                .map(|station_type| {
+                    item(
+                        station_type.as_str(),
+                        Some(format!("{station_type:?}")),
+                        lsp::CompletionItemKind::ENUM_MEMBER,
+                    )
+                })
+                .collect(),
+            _ => return None,
+        };
+        Some(lsp::CompletionResponse::Array(items))
+    }
+}
+
+struct Cursor {
+    fir_name: String,
+    kind: FileKind,
+    /// Id-like word under the cursor, possibly empty
+    token: String,
+    /// Key whose value the cursor is in, `None` in table headers
+    key: Option<String>,
+    /// FIR a position reference at the cursor points into
+    ref_fir: String,
+}
+
+fn extract<N: LspNotification>(notification: Notification) -> Option<N::Params> {
+    notification
+        .extract(N::METHOD)
+        .map_err(|e| warn!("invalid notification: {e:?}"))
+        .ok()
+}
+
+fn respond<R: LspRequest>(
+    request: Request,
+    handler: impl FnOnce(R::Params) -> R::Result,
+) -> Result<serde_json::Value, BoxError> {
+    let (_, params) = request.extract::<R::Params>(R::METHOD)?;
+    Ok(serde_json::to_value(handler(params))?)
+}
+
+fn uri_to_path(uri: &lsp::Uri) -> PathBuf {
+    PathBuf::from(
+        uri.path()
+            .as_estr()
+            .decode()
+            .into_string_lossy()
+            .into_owned(),
+    )
+}
+
+fn path_to_uri(path: &Path) -> lsp::Uri {
+    let encoded = path
+        .to_string_lossy()
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect::<String>();
+    lsp::Uri::from_str(&format!("file://{encoded}")).expect("percent-encoded path is a valid URI")
+}
+
+/// Byte offset of an LSP position, which counts UTF-16 code units
+fn offset_at(contents: &str, position: lsp::Position) -> usize {
+    let line_start = contents
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+    let line = contents[line_start..].lines().next().unwrap_or_default();
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= position.character as usize {
+            return line_start + idx;
+        }
+        units += c.len_utf16();
+    }
+    line_start + line.len()
+}
+
+fn position_at(contents: &str, offset: usize) -> lsp::Position {
+    let before = &contents[..offset.min(contents.len())];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    lsp::Position::new(
+        u32::try_from(before.matches('\n').count()).unwrap_or(u32::MAX),
+        u32::try_from(before[line_start..].encode_utf16().count()).unwrap_or(u32::MAX),
+    )
+}
+
+fn line_range(contents: &str, line: usize) -> lsp::Range {
+    let length = contents
+        .lines()
+        .nth(line)
+        .map_or(0, |line| line.encode_utf16().count());
+    let line = u32::try_from(line).unwrap_or(u32::MAX);
+    lsp::Range::new(
+        lsp::Position::new(line, 0),
+        lsp::Position::new(line, u32::try_from(length).unwrap_or(u32::MAX)),
+    )
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-'
+}
+
+/// Byte range of the id-like word around `offset`
+fn token_at(contents: &str, offset: usize) -> (usize, usize) {
+    let start = contents[..offset]
+        .rfind(|c| !is_id_char(c))
+        .map_or(0, |idx| idx + 1);
+    let end = contents[offset..]
+        .find(|c| !is_id_char(c))
+        .map_or(contents.len(), |idx| offset + idx);
+    (start, end)
+}
+
+/// Key of the value at `offset`, e.g. `id` in `{ id = "MMR" }` or `volumes` in a multi-line
+/// array, `None` in a table header.
+fn key_before(contents: &str, offset: usize) -> Option<String> {
+    let line_start = contents[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+    let line = &contents[line_start..offset];
+    if line.trim_start().starts_with('[') && !line.contains('=') {
+        return None;
+    }
+    let equals = contents[..offset].rfind('=')?;
+    let key = contents[..equals].trim_end();
+    let key_start = key.rfind(|c| !is_id_char(c)).map_or(0, |idx| idx + 1);
+    Some(key[key_start..].to_string())
+}
+
+/// Value of `fir` in the inline table around `offset`
+fn inline_table_fir(contents: &str, offset: usize) -> Option<String> {
+    let start = contents[..offset].rfind('{')?;
+    let end = offset + contents[offset..].find('}')?;
+    let table = &contents[start + 1..end];
+    let fir_key = table
+        .match_indices("fir")
+        .find(|(idx, _)| table[idx + 3..].trim_start().starts_with('='))?
+        .0;
+    let value = table[fir_key + 3..].trim_start()[1..].trim_start();
+    let value = value.strip_prefix('"')?;
+    Some(value[..value.find('"')?].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, str::FromStr};
+
+    use lsp_server::{Connection, Message, Notification, Request, RequestId};
+    use lsp_types::{
+        self as lsp,
+        notification::{
+            DidCloseTextDocument, DidOpenTextDocument, Exit, Initialized, Notification as _,
+            PublishDiagnostics,
+        },
+        request::{Completion, GotoDefinition, HoverRequest, Initialize, Request as _, Shutdown},
+    };
+    use serde_json::{json, Value};
+
+    fn request(client: &Connection, id: i32, method: &str, params: Value) -> Value {
+        client
+            .sender
+            .send(Message::Request(Request::new(
+                RequestId::from(id),
+                method.to_string(),
+                params,
+            )))
+            .unwrap();
+        loop {
+            if let Message::Response(response) = client.receiver.recv().unwrap() {
+                assert_eq!(response.id, RequestId::from(id));
+                return response.result.unwrap();
+            }
+        }
+    }
+
+    fn next_diagnostics(client: &Connection) -> lsp::PublishDiagnosticsParams {
+        loop {
+            if let Message::Notification(notification) = client.receiver.recv().unwrap() {
+                return notification.extract(PublishDiagnostics::METHOD).unwrap();
+            }
+        }
+    }
+
+    fn open(client: &Connection, path: &Path, text: String) {
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                DidOpenTextDocument::METHOD.to_string(),
+                lsp::DidOpenTextDocumentParams {
+                    text_document: lsp::TextDocumentItem::new(
+                        super::path_to_uri(path),
+                        "toml".to_string(),
+                        1,
+                        text,
+                    ),
+                },
+            )))
+            .unwrap();
+    }
+
+    /// Reloading of the config and volumes, with the diagnostic of `rule` left in sectors.toml
+    fn check_reloads(client: &Connection, root: &Path, rule: &str) {
+        // an open config is used instead of the one on disk
+        let uri = super::path_to_uri(&root.join("FIRs/EDMM/sectors.toml"));
+        let config_path = root.join("config.toml");
+        open(
+            client,
+            &config_path,
+            format!("[vateud8]\n[firs.EDMM]\n[firs.EDGG]\n[lints.rules]\n{rule} = \"off\"\n"),
+        );
+        let configured = next_diagnostics(client);
+        assert_eq!(configured.uri, uri);
+        assert!(configured.diagnostics.is_empty());
+
+        let volumes_path = root.join("FIRs/EDMM/volumes.geojson");
+        open(client, &volumes_path, "{".to_string());
+        let broken = next_diagnostics(client);
+        assert_eq!(broken.uri, super::path_to_uri(&volumes_path));
+        assert_eq!(
+            broken.diagnostics[0].code,
+            Some(lsp::NumberOrString::String("read-error".to_string()))
+        );
+
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                DidCloseTextDocument::METHOD.to_string(),
+                lsp::DidCloseTextDocumentParams {
+                    text_document: lsp::TextDocumentIdentifier::new(super::path_to_uri(
+                        &config_path,
+                    )),
+                },
+            )))
+            .unwrap();
+        let closed = next_diagnostics(client);
+        assert_eq!(closed.uri, uri);
+        assert_eq!(closed.diagnostics.len(), 1);
+    }
+
+    #[test]
+    fn test_server() {
+        let root = std::env::temp_dir().join(format!("vod-lsp-{}", std::process::id()));
+        fs::create_dir_all(root.join("FIRs/EDMM")).unwrap();
+        fs::create_dir_all(root.join("FIRs/EDGG")).unwrap();
+        fs::write(
+            root.join("config.toml"),
+            "[vateud8]\n[firs.EDMM]\n[firs.EDGG]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("FIRs/EDGG/positions.toml"),
+            "[GIN]\nfrequency = 127725000\nprefix = \"EDGG\"\nstation_type = \"CTR\"\n\
+             radio_callsign = \"Langen Radar\"\n",
+        )
+        .unwrap();
+        let sectors_path = root.join("FIRs/EDMM/sectors.toml");
+        let sectors = "[TEST]\nvolumes = []\nposition_priority = [[{ fir = \"EDGG\", id = \"GIN\" }, { id = \"XXX\" }]]\n";
+        fs::write(&sectors_path, sectors).unwrap();
+
+        let (server, client) = Connection::memory();
+        let server_thread = std::thread::spawn(move || super::serve(&server).unwrap());
+        let root_uri = super::path_to_uri(&root);
+        request(
+            &client,
+            1,
+            Initialize::METHOD,
+            json!({ "capabilities": {}, "rootUri": root_uri }),
+        );
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                Initialized::METHOD.to_string(),
+                json!({}),
+            )))
+            .unwrap();
+
+        let initial = next_diagnostics(&client);
+        assert_eq!(initial.uri, super::path_to_uri(&sectors_path));
+        assert_eq!(initial.diagnostics.len(), 1);
+        assert_eq!(initial.diagnostics[0].range.start.line, 0);
+
+        // unsaved edits are checked, the broken reference is fixed in the editor
+        let uri = super::path_to_uri(&sectors_path);
+        let edited = sectors.replace("{ id = \"XXX\" }", "{ fir = \"EDGG\", id = \"\" }");
+        open(&client, &sectors_path, edited.clone());
+        let updated = next_diagnostics(&client);
+        assert_eq!(updated.diagnostics.len(), 1);
+        assert!(updated.diagnostics[0].message.contains("EDGG-"));
+
+        let position = |needle: &str| {
+            let offset = edited.find(needle).unwrap() + 1;
+            json!({ "textDocument": { "uri": uri }, "position": super::position_at(&edited, offset) })
+        };
+        let definition = request(&client, 2, GotoDefinition::METHOD, position("GIN"));
+        assert_eq!(
+            definition["uri"],
+            json!(super::path_to_uri(&root.join("FIRs/EDGG/positions.toml")))
+        );
+        assert_eq!(definition["range"]["start"]["line"], 0);
+
+        let hover = request(&client, 3, HoverRequest::METHOD, position("GIN"));
+        let hover = hover["contents"]["value"].as_str().unwrap();
+        assert!(hover.contains("**EDGG_CTR** Langen Radar"));
+        assert!(hover.contains("127.725 MHz"));
+
+        let completion = request(&client, 4, Completion::METHOD, position("\" }]]"));
+        assert_eq!(completion[0]["label"], "GIN");
+        assert_eq!(completion[0]["detail"], "EDGG_CTR");
+
+        let Some(lsp::NumberOrString::String(rule)) = &updated.diagnostics[0].code else {
+            unreachable!("diagnostics have a rule id");
+        };
+        check_reloads(&client, &root, rule);
+
+        request(&client, 5, Shutdown::METHOD, Value::Null);
+        client
+            .sender
+            .send(Message::Notification(Notification::new(
+                Exit::METHOD.to_string(),
+                Value::Null,
+            )))
+            .unwrap();
+        server_thread.join().unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            super::uri_to_path(&lsp::Uri::from_str("file:///a%20b/c.toml").unwrap()),
+            Path::new("/a b/c.toml")
+        );
+    }
+}
@@ -1,40 +1,996 @@
 use std::{
-    env::{self, args_os},
-    io,
-    path::Path,
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process::{self, ExitCode},
+    sync::mpsc,
+    time::Duration,
 };
 
-use tracing::error;
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use geo::Point;
+use itertools::Itertools;
+use notify::{RecursiveMode, Watcher};
+use serde_json::json;
+use similar::TextDiff;
+use tracing::{error, info};
 use tracing_subscriber::EnvFilter;
 use vatsim_open_data::{
-    vateud8::{self},
-    OpenData,
+    aixm,
+    diagnostic::{self, Diagnostic, Severity},
+    diff, euroscope, insert_unique, lint, openair, render,
+    vateud8::{
+        self, CachedSnapshot, CachedSource, Fix, ListFile, PositionSource, Vateud8Data, Vateud8Page,
+    },
+    Config, OpenData, Volume, DEFAULT_ARC_RESOLUTION, FIR,
 };
 
-fn main() -> Result<(), vatsim_open_data::Error> {
+/// Parse, validate and process the VATSIM open data repository
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Root of the open data repository, containing `config.toml` and `FIRs/`
+    #[arg(short = 'C', long, global = true, default_value = ".")]
+    data_dir: PathBuf,
+    /// Only consider these FIRs, may be given multiple times
+    #[arg(long = "fir", global = true)]
+    firs: Vec<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate the data set, exits with 1 if any check fails with severity error
+    Check(CheckArgs),
+    /// Write the data set in another format
+    Export(ExportArgs),
+    /// Look up airspace and positions
+    #[command(subcommand)]
+    Query(Query),
+    /// List entities changed between the data set and another one, exits with 1 on changes
+    Diff {
+        /// Root of the other open data repository
+        other: PathBuf,
+    },
+    /// Rewrite data files in canonical form, comments are not preserved
+    Fmt {
+        /// Only report files that are not formatted, exits with 1 if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Create or replace the data of a FIR folder from external airspace data, keeping its
+    /// `fir.toml`
+    Import(ImportArgs),
+    /// Check again whenever a data file changes, printing new and resolved problems
+    Watch(Vateud8Args),
+    /// Work with the VATEUD8 position list
+    #[command(subcommand)]
+    Vateud8(Vateud8Command),
+    /// Serve the data set as read-only JSON API, reloading it when files change
+    #[cfg(feature = "server")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum Vateud8Command {
+    /// Save the position list as JSON snapshot, e.g. for --vateud8-snapshot
+    Snapshot {
+        output: PathBuf,
+        #[command(flatten)]
+        source: Vateud8Source,
+    },
+    /// Propose fixes for positions and entries that do not match, exits with 1 if there are any
+    Fix {
+        /// Print the frequency and prefix fixes as patch of the positions.toml files instead
+        #[arg(long)]
+        patch: bool,
+        #[command(flatten)]
+        source: Vateud8Source,
+    },
+    /// List the additions, changes and removals to request from VATEUD8 so that the list matches
+    /// the data, exits with 1 if there are any
+    Submission {
+        #[command(flatten)]
+        source: Vateud8Source,
+    },
+    /// Compare VATEUD8 update dates with the last change of the data, exits with 1 if VATEUD8
+    /// was updated after the data of any position
+    Staleness {
+        /// Number of entries verified longest ago to list per region
+        #[arg(long, default_value_t = 5)]
+        limit: usize,
+        #[command(flatten)]
+        source: Vateud8Source,
+    },
+}
+
+#[derive(Args)]
+struct Vateud8Args {
+    /// Skip the comparison against the VATEUD8 position list
+    #[arg(long)]
+    no_vateud8: bool,
+    #[command(flatten)]
+    source: Vateud8Source,
+}
+
+#[derive(Args)]
+struct Vateud8Source {
+    /// Alternative location of the VATEUD8 position list
+    #[arg(long = "vateud8-url", env = "VATSIM_OPEN_DATA_VATEUD8_URL")]
+    url: Option<String>,
+    /// Compare against a local CSV or JSON position list instead of the VATEUD8 list
+    #[arg(long = "vateud8-file", conflicts_with_all = ["url", "snapshot", "cache"])]
+    file: Option<PathBuf>,
+    /// Use a snapshot saved with `vateud8 snapshot` instead of fetching the list
+    #[arg(long = "vateud8-snapshot", conflicts_with_all = ["url", "cache"])]
+    snapshot: Option<PathBuf>,
+    /// Keep the fetched list in this snapshot file, reusing it while younger than
+    /// --vateud8-max-age and falling back to it when fetching fails
+    #[arg(long = "vateud8-cache", env = "VATSIM_OPEN_DATA_VATEUD8_CACHE")]
+    cache: Option<PathBuf>,
+    /// Maximum age in hours of the cached list
+    #[arg(long = "vateud8-max-age", default_value_t = 24, requires = "cache")]
+    max_age: u32,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    #[command(flatten)]
+    vateud8: Vateud8Args,
+    /// How to report problems
+    #[arg(long, value_enum, default_value_t)]
+    format: DiagnosticFormat,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum DiagnosticFormat {
+    /// One line per problem
+    #[default]
+    Text,
+    /// One JSON object per line
+    Json,
+    /// SARIF 2.1.0 log for code scanning
+    Sarif,
+    /// GitHub Actions workflow commands, shown as annotations in pull requests
+    Github,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum ExportFormat {
+    /// Whole data set including config
+    #[default]
+    Json,
+    /// Volumes as `GeoJSON` feature collection
+    Geojson,
+    /// Volumes as `OpenAir` airspaces
+    Openair,
+    /// Map of a single FIR, select it with --fir
+    Svg,
+    /// Map of a single FIR as image, select it with --fir
+    #[cfg(feature = "png")]
+    Png,
+    /// Positions as VATEUD8 table in CSV, for FIRs with `vateud8_region`
+    Vateud8,
+    /// Normalized `SQLite` database, requires --output
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum Projection {
+    #[default]
+    Equirectangular,
+    Mercator,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    #[arg(long, value_enum, default_value_t)]
+    format: ExportFormat,
+    /// Output file, standard output if not given
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Only include volumes covering this flight level (svg, png)
+    #[arg(long)]
+    level: Option<u64>,
+    /// Map projection (svg, png)
+    #[arg(long, value_enum, default_value_t)]
+    projection: Projection,
+}
+
+#[derive(Subcommand)]
+enum Query {
+    /// Volumes and sectors at a location
+    Point {
+        #[arg(allow_negative_numbers = true)]
+        lat: f64,
+        #[arg(allow_negative_numbers = true)]
+        lon: f64,
+        /// Flight level, all levels if not given
+        #[arg(long)]
+        level: Option<u64>,
+    },
+    /// Positions a callsign matches, most specific first
+    Callsign { callsign: String },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImportFormat {
+    /// `OpenAir` file
+    Openair,
+    /// AIXM 5.1 file
+    Aixm,
+    /// `EuroScope` `.ese` file, optionally followed by a `.sct` file
+    Euroscope,
+}
+
+#[derive(Args)]
+struct ImportArgs {
+    #[arg(long, value_enum)]
+    format: ImportFormat,
+    /// Name of the FIR folder to write
+    name: String,
+    /// Input files
+    #[arg(required = true)]
+    files: Vec<PathBuf>,
+    /// Step in degrees when approximating arcs and circles
+    #[arg(long, default_value_t = DEFAULT_ARC_RESOLUTION, value_parser = parse_resolution)]
+    resolution: f64,
+}
+
+fn parse_resolution(value: &str) -> Result<f64, String> {
+    let resolution = value.parse::<f64>().map_err(|e| e.to_string())?;
+    if resolution.is_finite() && resolution > 0.0 {
+        Ok(resolution)
+    } else {
+        Err("must be a positive number of degrees".to_string())
+    }
+}
+
+/// Exit code for checks that found problems
+const EXIT_FINDINGS: u8 = 1;
+/// Exit code for failures to run at all, e.g. unreadable data
+const EXIT_ERROR: u8 = 2;
+
+fn main() -> ExitCode {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_env("VATSIM_OPEN_DATA_LOG"))
         .with_writer(io::stderr)
         .init();
 
-    // FIXME clap
-    let open_data = OpenData::from_path(Path::new(&args_os().nth(1).unwrap()))?;
-
-    if let Err(es) = open_data.run_checks() {
-        for e in es {
+    let cli = Cli::parse();
+    match run(&cli) {
+        Ok(code) => code,
+        Err(e) => {
             error!("{e}");
+            ExitCode::from(EXIT_ERROR)
         }
     }
+}
 
-    // TODO cli disable flag
-    let vateud8 = vateud8::get(env::var("VATSIM_OPEN_DATA_VATEUD8_URL").ok().as_deref()).unwrap();
-    if let Err(es) = vateud8.check(&open_data) {
-        for e in es {
-            error!("{e}");
+fn run(cli: &Cli) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    match &cli.command {
+        Command::Check(args) => check(cli, args),
+        Command::Export(args) => export(&load(cli)?, cli, args),
+        Command::Query(query) => self::query(&load(cli)?, query),
+        Command::Diff { other } => {
+            let changes = diff::diff(
+                &load(cli)?,
+                &filter_firs(OpenData::from_path(other)?, &cli.firs)?,
+            );
+            for change in &changes {
+                println!("{change}");
+            }
+            Ok(exit_code(changes.is_empty()))
+        }
+        Command::Fmt { check } => fmt(cli, *check),
+        Command::Import(args) => import(cli, args),
+        Command::Watch(args) => watch(cli, args),
+        Command::Vateud8(Vateud8Command::Snapshot { output, source }) => {
+            let data = load_vateud8(source)?;
+            data.save(output)?;
+            info!("saved {} positions", data.positions().len());
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Vateud8(Vateud8Command::Fix { patch, source }) => vateud8_fix(cli, *patch, source),
+        Command::Vateud8(Vateud8Command::Submission { source }) => {
+            let submission = load_vateud8(source)?.submission(&load(cli)?);
+            for change in &submission {
+                println!("{change}");
+            }
+            Ok(exit_code(submission.is_empty()))
+        }
+        Command::Vateud8(Vateud8Command::Staleness { limit, source }) => {
+            vateud8_staleness(cli, *limit, source)
+        }
+        #[cfg(feature = "server")]
+        Command::Serve { address } => serve(cli, address),
+    }
+}
+
+fn exit_code(success: bool) -> ExitCode {
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(EXIT_FINDINGS)
+    }
+}
+
+fn load(cli: &Cli) -> Result<OpenData, Box<dyn std::error::Error>> {
+    filter_firs(OpenData::from_path(&cli.data_dir)?, &cli.firs)
+}
+
+fn ensure_known(open_data: &OpenData, firs: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    match firs.iter().find(|fir| !open_data.firs.contains_key(*fir)) {
+        Some(unknown) => Err(format!("unknown FIR {unknown}").into()),
+        None => Ok(()),
+    }
+}
+
+fn filter_firs(
+    mut open_data: OpenData,
+    firs: &[String],
+) -> Result<OpenData, Box<dyn std::error::Error>> {
+    ensure_known(&open_data, firs)?;
+    if !firs.is_empty() {
+        open_data.firs.retain(|name, _| firs.contains(name));
+    }
+    Ok(open_data)
+}
+
+fn check(cli: &Cli, args: &CheckArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    // cross-FIR references need the whole data set, only the reported errors are filtered
+    let (open_data, read_errors) = OpenData::from_path_with_errors(&cli.data_dir)?;
+    ensure_known(&open_data, &cli.firs)?;
+    let vateud8 = fetch_vateud8(&args.vateud8);
+    let errors = open_data.run_checks().err().unwrap_or_default();
+    let diagnostics = diagnostics(
+        cli,
+        &open_data,
+        &read_errors.iter().chain(&errors).collect_vec(),
+        vateud8.as_ref().map(Option::as_ref),
+    );
+
+    match args.format {
+        DiagnosticFormat::Text => {
+            for diagnostic in &diagnostics {
+                println!("{diagnostic}");
+            }
         }
+        DiagnosticFormat::Json => print!("{}", diagnostic::to_json_lines(&diagnostics)),
+        DiagnosticFormat::Sarif => println!(
+            "{}",
+            serde_json::to_string_pretty(&diagnostic::to_sarif(&diagnostics))?
+        ),
+        DiagnosticFormat::Github => print!("{}", diagnostic::to_github(&diagnostics)),
     }
 
-    println!("{}", serde_json::to_string_pretty(&open_data).unwrap());
+    Ok(exit_code(
+        diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity < Severity::Error),
+    ))
+}
+
+/// The VATEUD8 list unless skipped, failures to get it are reported as diagnostic
+fn fetch_vateud8(args: &Vateud8Args) -> Result<Option<Vateud8Data>, vateud8::Error> {
+    if args.no_vateud8 {
+        info!("skipping VATEUD8 checks");
+        Ok(None)
+    } else {
+        load_vateud8(&args.source).map(Some)
+    }
+}
 
+fn vateud8_fix(
+    cli: &Cli,
+    patch: bool,
+    source: &Vateud8Source,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let open_data = load(cli)?;
+    let fixes = load_vateud8(source)?.fixes(&open_data);
+    if patch {
+        for fir_name in fixes
+            .iter()
+            .filter_map(|fix| match fix {
+                Fix::Frequency { fir, .. } | Fix::Prefix { fir, .. } => Some(fir),
+                _ => None,
+            })
+            .unique()
+        {
+            let file = Path::new("FIRs").join(fir_name).join("positions.toml");
+            let old = fs::read_to_string(cli.data_dir.join(&file))?;
+            let new = vateud8::patch_positions(&old, fir_name, &fixes);
+            print!(
+                "{}",
+                TextDiff::from_lines(&old, &new).unified_diff().header(
+                    &format!("a/{}", file.display()),
+                    &format!("b/{}", file.display())
+                )
+            );
+        }
+        return Ok(exit_code(fixes.is_empty()));
+    }
+
+    for fix in &fixes {
+        println!("{fix}");
+    }
+    let ignore_extra = fixes
+        .iter()
+        .filter_map(|fix| match fix {
+            Fix::IgnoreExtra { name } => Some(name),
+            _ => None,
+        })
+        .collect_vec();
+    let ignore_positions = fixes
+        .iter()
+        .filter_map(|fix| match fix {
+            Fix::IgnorePosition { fir, position } => Some((fir, position)),
+            _ => None,
+        })
+        .into_group_map();
+    if !ignore_extra.is_empty() || !ignore_positions.is_empty() {
+        println!("\n# config.toml entries to ignore the rest");
+    }
+    if !ignore_extra.is_empty() {
+        println!("[vateud8]\nignore_extra = {ignore_extra:?}");
+    }
+    for (fir, positions) in ignore_positions.iter().sorted() {
+        println!("[firs.{fir}]\nvateud8_ignore = {positions:?}");
+    }
+    Ok(exit_code(fixes.is_empty()))
+}
+
+fn vateud8_staleness(
+    cli: &Cli,
+    limit: usize,
+    source: &Vateud8Source,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let open_data = load(cli)?;
+    let vateud8 = load_vateud8(source)?;
+    let staleness = vateud8.staleness(&open_data, |fir| {
+        last_change(
+            &cli.data_dir,
+            &Path::new("FIRs").join(fir).join("positions.toml"),
+        )
+    });
+    let outdated = staleness.iter().filter(|s| s.is_outdated()).collect_vec();
+    if !outdated.is_empty() {
+        println!("VATEUD8 updated after the data:");
+    }
+    for s in &outdated {
+        println!(
+            "  {}-{} ({}): VATEUD8 {}, data {}",
+            s.fir,
+            s.position,
+            s.reference,
+            s.vateud8_updated.map_or("-".to_string(), |d| d.to_string()),
+            s.data_updated.map_or("-".to_string(), |d| d.to_string())
+        );
+    }
+    for (region, references) in vateud8.oldest_unverified(limit) {
+        println!("Oldest entries of region {region}:");
+        for reference in references {
+            println!(
+                "  {}: {}",
+                reference.name,
+                reference
+                    .updated_at
+                    .map_or("never updated".to_string(), |d| d.to_string())
+            );
+        }
+    }
+    Ok(exit_code(outdated.is_empty()))
+}
+
+/// Commit date of the last change of `path` relative to `data_dir`, the modification date
+/// outside of git repositories
+fn last_change(data_dir: &Path, path: &Path) -> Option<NaiveDate> {
+    let git = process::Command::new("git")
+        .arg("-C")
+        .arg(data_dir)
+        .args(["log", "-1", "--format=%cs", "--"])
+        .arg(path)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|date| date.trim().parse().ok());
+    git.or_else(|| {
+        let modified = fs::metadata(data_dir.join(path)).ok()?.modified().ok()?;
+        Some(DateTime::<Utc>::from(modified).date_naive())
+    })
+}
+
+fn load_vateud8(source: &Vateud8Source) -> Result<Vateud8Data, vateud8::Error> {
+    if let Some(path) = &source.file {
+        return ListFile { path: path.clone() }.load();
+    }
+    if let Some(path) = &source.snapshot {
+        return CachedSnapshot { path: path.clone() }.load();
+    }
+    let page = Vateud8Page {
+        url: source.url.clone(),
+    };
+    match &source.cache {
+        Some(path) => CachedSource {
+            source: page,
+            path: path.clone(),
+            max_age: TimeDelta::hours(source.max_age.into()),
+        }
+        .load(),
+        None => page.load(),
+    }
+}
+
+/// Turns `errors` and VATEUD8 mismatches into located diagnostics for the FIRs selected on the
+/// command line, with the lint config applied.
+fn diagnostics(
+    cli: &Cli,
+    open_data: &OpenData,
+    errors: &[&vatsim_open_data::Error],
+    vateud8: Result<Option<&Vateud8Data>, &vateud8::Error>,
+) -> Vec<Diagnostic> {
+    let selected = |firs: Vec<&str>| {
+        cli.firs.is_empty() || firs.iter().any(|fir| cli.firs.iter().any(|f| f == fir))
+    };
+
+    let (vateud8, fetch_error) = match vateud8 {
+        Ok(vateud8) => (vateud8, None),
+        Err(e) => (None, Some(Diagnostic::from(e))),
+    };
+    let vateud8_errors = vateud8
+        .and_then(|vateud8| vateud8.check(open_data).err())
+        .unwrap_or_default();
+    let diagnostics = errors
+        .iter()
+        .filter(|e| selected(e.firs()))
+        .map(|e| Diagnostic::from(*e))
+        .chain(
+            vateud8_errors
+                .iter()
+                .filter(|e| selected(e.firs()))
+                .map(Diagnostic::from),
+        )
+        .chain(fetch_error)
+        .chain(
+            vateud8
+                .map(Vateud8Data::row_errors)
+                .unwrap_or_default()
+                .iter()
+                .map(Diagnostic::from),
+        )
+        .collect();
+
+    let mut diagnostics = lint::apply(&open_data.config, diagnostics, |rule, fir| {
+        (vateud8.is_some() || !rule.starts_with("vateud8-"))
+            && (cli.firs.is_empty() || fir.is_some_and(|fir| cli.firs.iter().any(|f| f == fir)))
+    });
+    diagnostic::locate(&mut diagnostics, &cli.data_dir);
+    diagnostics
+}
+
+fn watch(cli: &Cli, args: &Vateud8Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let data_dir = cli.data_dir.canonicalize()?;
+    let (mut open_data, errors) = OpenData::from_path_with_errors(&data_dir)?;
+    ensure_known(&open_data, &cli.firs)?;
+    // reported until the files parse again
+    let mut read_errors = errors
+        .into_iter()
+        .into_group_map_by(|e| e.firs()[0].to_string())
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+    let vateud8 = fetch_vateud8(args);
+    let mut fir_errors = open_data
+        .firs
+        .keys()
+        .map(|fir_name| (fir_name.clone(), open_data.fir_checks(fir_name)))
+        .collect::<BTreeMap<_, _>>();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watch_data_dir(&mut watcher, &data_dir)?;
+
+    let mut reported = Vec::new();
+    loop {
+        let cross_fir_errors = open_data.cross_fir_checks();
+        let errors = read_errors
+            .values()
+            .chain(fir_errors.values())
+            .flatten()
+            .chain(&cross_fir_errors)
+            .collect_vec();
+        let current = diagnostics(
+            cli,
+            &open_data,
+            &errors,
+            vateud8.as_ref().map(Option::as_ref),
+        );
+        let lines = current.iter().map(ToString::to_string).collect::<Vec<_>>();
+        for line in lines.iter().filter(|line| !reported.contains(*line)) {
+            println!("+ {line}");
+        }
+        for line in reported.iter().filter(|line| !lines.contains(*line)) {
+            println!("- {line}");
+        }
+        let count = |severity| {
+            current
+                .iter()
+                .filter(|diagnostic| diagnostic.severity == severity)
+                .count()
+        };
+        println!(
+            "{} errors, {} warnings, {} infos",
+            count(Severity::Error),
+            count(Severity::Warning),
+            count(Severity::Info)
+        );
+        reported = lines;
+
+        let paths = next_changes(&rx)?;
+        for (fir_name, errors) in reload(&mut open_data, &data_dir, &paths, &[]) {
+            read_errors.insert(fir_name.clone(), errors);
+            if open_data.firs.contains_key(&fir_name) {
+                let errors = open_data.fir_checks(&fir_name);
+                fir_errors.insert(fir_name, errors);
+            } else {
+                fir_errors.remove(&fir_name);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve(cli: &Cli, address: &str) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    use std::sync::{PoisonError, RwLock};
+
+    let data_dir = cli.data_dir.canonicalize()?;
+    let open_data = RwLock::new(load(cli)?);
+    let server = vatsim_open_data::server::bind(address)?;
+    info!("listening on {address}");
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watch_data_dir(&mut watcher, &data_dir)?;
+    std::thread::scope(|scope| {
+        scope.spawn(|| vatsim_open_data::server::serve(&server, &open_data));
+        let result = (|| loop {
+            let paths = next_changes(&rx)?;
+            let mut open_data = open_data.write().unwrap_or_else(PoisonError::into_inner);
+            reload(&mut open_data, &data_dir, &paths, &cli.firs);
+        })();
+        server.unblock();
+        result
+    })
+}
+
+fn watch_data_dir(
+    watcher: &mut impl Watcher,
+    data_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    watcher.watch(&data_dir.join("FIRs"), RecursiveMode::Recursive)?;
+    watcher.watch(&data_dir.join("config.toml"), RecursiveMode::NonRecursive)?;
     Ok(())
 }
+
+/// Blocks until files changed, collecting changes until the editor is done saving
+fn next_changes(
+    rx: &mpsc::Receiver<notify::Result<notify::Event>>,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let mut paths = Vec::new();
+    while paths.is_empty() {
+        paths.extend(changed_paths(rx.recv()??));
+    }
+    while let Ok(event) = rx.recv_timeout(Duration::from_millis(200)) {
+        paths.extend(changed_paths(event?));
+    }
+    Ok(paths)
+}
+
+/// Reloads the config and FIR folders `paths` belong to, returns the names of reloaded or
+/// removed FIRs with the data files that could not be read. FIRs with such files keep their
+/// previous data. Only FIRs in `only` are loaded unless it is empty.
+fn reload(
+    open_data: &mut OpenData,
+    data_dir: &Path,
+    paths: &[PathBuf],
+    only: &[String],
+) -> BTreeMap<String, Vec<vatsim_open_data::Error>> {
+    let firs_dir = data_dir.join("FIRs");
+    let config_path = data_dir.join("config.toml");
+    if paths.contains(&config_path) {
+        match Config::from_toml(&config_path) {
+            Ok(config) => open_data.config = config,
+            Err(e) => error!("keeping previous config: {e}"),
+        }
+    }
+    let changed_firs = paths
+        .iter()
+        .filter_map(|path| path.strip_prefix(&firs_dir).ok()?.iter().next())
+        .map(|fir_name| fir_name.to_string_lossy().to_string())
+        .filter(|fir_name| only.is_empty() || only.contains(fir_name))
+        .collect::<BTreeSet<_>>();
+    changed_firs
+        .into_iter()
+        .map(|fir_name| {
+            let folder = firs_dir.join(&fir_name);
+            if !folder.is_dir() {
+                info!("removing FIR {fir_name}");
+                open_data.firs.remove(&fir_name);
+                return (fir_name, vec![]);
+            }
+            info!("reloading FIR {fir_name}");
+            let (fir, errors) = FIR::read_folder(&folder);
+            if errors.is_empty() {
+                open_data.firs.insert(fir_name.clone(), fir);
+            }
+            // a half-saved file must not swap the FIR for an empty one
+            let errors = errors
+                .into_iter()
+                .map(|(file, e)| {
+                    error!(
+                        "keeping previous FIR {fir_name}: {}: {e}",
+                        folder.join(file).display()
+                    );
+                    vatsim_open_data::Error::InvalidDataFile(fir_name.clone(), file, Box::new(e))
+                })
+                .collect();
+            (fir_name, errors)
+        })
+        .collect()
+}
+
+fn changed_paths(event: notify::Event) -> Vec<PathBuf> {
+    if event.kind.is_create() || event.kind.is_modify() || event.kind.is_remove() {
+        event.paths
+    } else {
+        vec![]
+    }
+}
+
+fn export(
+    open_data: &OpenData,
+    cli: &Cli,
+    args: &ExportArgs,
+) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let volumes = || {
+        open_data
+            .firs
+            .iter()
+            .flat_map(|(fir_name, fir)| {
+                fir.volumes.iter().map(move |(id, volume)| {
+                    // ids are only unique within a FIR
+                    let id = if open_data.firs.len() == 1 {
+                        id.clone()
+                    } else {
+                        format!("{fir_name}/{id}")
+                    };
+                    (id, volume)
+                })
+            })
+            .collect::<Vec<(String, &Volume)>>()
+    };
+
+    let single_fir = || match &cli.firs[..] {
+        [fir] => Ok(fir),
+        _ => Err("map export needs exactly one --fir"),
+    };
+    let render_options = render::Options {
+        projection: match args.projection {
+            Projection::Equirectangular => render::Projection::Equirectangular,
+            Projection::Mercator => render::Projection::Mercator,
+        },
+        level: args.level,
+        ..Default::default()
+    };
+
+    let output = match args.format {
+        ExportFormat::Json => serde_json::to_string_pretty(open_data)? + "\n",
+        ExportFormat::Geojson => {
+            let volumes = volumes();
+            serde_json::to_string_pretty(&Volume::to_geojson(
+                volumes.iter().map(|(id, volume)| (id, *volume)),
+            ))? + "\n"
+        }
+        ExportFormat::Openair => {
+            let volumes = volumes();
+            openair::export(volumes.iter().map(|(id, volume)| (id, *volume)))
+        }
+        ExportFormat::Svg => render::svg(open_data, single_fir()?, &render_options)?,
+        #[cfg(feature = "png")]
+        ExportFormat::Png => {
+            let png = render::png(open_data, single_fir()?, &render_options)?;
+            match &args.output {
+                Some(path) => fs::write(path, png)?,
+                None => io::Write::write_all(&mut io::stdout(), &png)?,
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        ExportFormat::Vateud8 => vateud8::to_table(&vateud8::export(open_data))?,
+        #[cfg(feature = "sqlite")]
+        ExportFormat::Sqlite => {
+            let output = args
+                .output
+                .as_deref()
+                .ok_or("sqlite export needs --output")?;
+            vatsim_open_data::sqlite::export(open_data, output)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, output)?,
+        None => print!("{output}"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn query(open_data: &OpenData, query: &Query) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let result = match query {
+        Query::Point { lat, lon, level } => {
+            let point = Point::new(*lon, *lat);
+            json!({
+                "volumes": open_data
+                    .volumes_at(point, *level)
+                    .into_iter()
+                    .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+                    .collect_vec(),
+                "sectors": open_data
+                    .sectors_at(point, *level)
+                    .into_iter()
+                    .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+                    .collect_vec(),
+            })
+        }
+        Query::Callsign { callsign } => open_data
+            .match_callsign(callsign)
+            .into_iter()
+            .map(|(fir, id, position)| json!({ "fir": fir, "id": id, "position": position }))
+            .collect(),
+    };
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(cli: &Cli, check: bool) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let mut unformatted = false;
+    let mut unreadable = 0;
+    for folder in fir_folders(&cli.data_dir)? {
+        let name = folder.file_name().unwrap_or_default().to_string_lossy();
+        if !cli.firs.is_empty() && !cli.firs.iter().any(|fir| *fir == name) {
+            continue;
+        }
+        let (fir, errors) = FIR::read_folder(&folder);
+        if !errors.is_empty() {
+            // formatting would drop the entities of the unreadable file
+            for (file, e) in errors {
+                error!("{}: {e}", folder.join(file).display());
+            }
+            unreadable += 1;
+            continue;
+        }
+        for (file, contents) in fir.to_files()? {
+            let path = folder.join(file);
+            if fs::read_to_string(&path).ok().as_deref() == Some(&contents) {
+                continue;
+            }
+            unformatted = true;
+            if check {
+                println!("{}", path.display());
+            } else {
+                info!("formatting {}", path.display());
+                fs::write(&path, contents)?;
+            }
+        }
+    }
+    if unreadable > 0 {
+        return Err(format!("{unreadable} FIR folders could not be read").into());
+    }
+    Ok(exit_code(!(check && unformatted)))
+}
+
+fn fir_folders(data_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(data_dir
+        .join("FIRs")
+        .read_dir()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .sorted()
+        .collect())
+}
+
+fn import(cli: &Cli, args: &ImportArgs) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let fir = match args.format {
+        ImportFormat::Openair => {
+            let mut fir = FIR::default();
+            for file in &args.files {
+                let volumes = openair::import(&fs::read_to_string(file)?, args.resolution)?;
+                insert_volumes(&mut fir, file, volumes);
+            }
+            fir
+        }
+        ImportFormat::Aixm => {
+            let mut fir = FIR::default();
+            for file in &args.files {
+                let import = aixm::import(file, args.resolution)?;
+                for unconverted in import.unconverted {
+                    error!(
+                        "could not convert {} in {}: {}",
+                        unconverted.id,
+                        file.display(),
+                        unconverted.reason
+                    );
+                }
+                insert_volumes(&mut fir, file, import.volumes);
+            }
+            fir
+        }
+        ImportFormat::Euroscope => {
+            let [ese, sct @ ..] = &args.files[..] else {
+                unreachable!("at least one file is required");
+            };
+            euroscope::import(ese, sct.first().map(PathBuf::as_path), args.resolution)?
+        }
+    };
+
+    // data files the import does not bring would otherwise mix old and new data
+    let folder = cli.data_dir.join("FIRs").join(&args.name);
+    for name in [
+        "positions.toml",
+        "sectors.toml",
+        "airports.toml",
+        "volumes.geojson",
+    ] {
+        match fs::remove_file(folder.join(name)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    fir.to_folder(&folder)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Adds the volumes imported from `file`, renaming those whose name an earlier file took
+fn insert_volumes(fir: &mut FIR, file: &Path, volumes: BTreeMap<String, Volume>) {
+    for (name, volume) in volumes {
+        if fir.volumes.contains_key(&name) {
+            error!(
+                "volume {name} in {} already imported from another file, renaming",
+                file.display()
+            );
+        }
+        insert_unique(&mut fir.volumes, &name, volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_last_change_without_git() {
+        // untracked files and folders outside of git repositories use the modification date
+        let dir = std::env::temp_dir().join(format!("vod-last-change-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let march = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        fs::File::create(dir.join("positions.toml"))
+            .unwrap()
+            .set_modified(march.into())
+            .unwrap();
+        let changed = super::last_change(&dir, Path::new("positions.toml"));
+        let missing = super::last_change(&dir, Path::new("sectors.toml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(changed, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(missing, None);
+    }
+}
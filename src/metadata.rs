@@ -0,0 +1,38 @@
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::FirName;
+
+/// Descriptive data of a FIR from the optional `fir.toml` in its folder
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FirMetadata {
+    /// Full name, e.g. `Muenchen`
+    pub name: Option<String>,
+    /// ICAO location indicator, e.g. `EDMM`
+    pub icao: Option<String>,
+    /// Folder name of the parent UIR, e.g. `EDUU`, whose folder holds the UIR-level data
+    pub uir: Option<String>,
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`
+    pub country: Option<String>,
+    pub vacc: Option<String>,
+    /// Default transition altitude in feet
+    pub transition_altitude: Option<u32>,
+    /// Folder names of the FIRs positions may be referenced from besides the UIR and its
+    /// members, any FIR if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub neighbours: Vec<FirName>,
+}
+
+impl FirMetadata {
+    #[cfg(feature = "fs")]
+    pub fn from_toml(path: &Path) -> Result<Self, super::Error> {
+        Self::from_toml_str(&read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<Self, super::Error> {
+        Ok(toml::from_str(contents)?)
+    }
+}
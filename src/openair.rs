@@ -0,0 +1,431 @@
+//! Conversion between [`Volume`]s and the `OpenAir` airspace text format.
+//!
+//! Arcs and circles are approximated by polygons, levels are converted to flight levels assuming
+//! standard pressure, `AGL` heights are treated as `AMSL`.
+
+use std::{collections::BTreeMap, fmt::Write};
+
+use geo::{Bearing, Coord, Distance, Haversine, LineString, Point, Polygon};
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{
+    geometry::{self, METERS_PER_FOOT, METERS_PER_NAUTICAL_MILE},
+    volume, Volume,
+};
+
+/// Airspace class written by [`export`], volumes carry no class information.
+const EXPORT_CLASS: &str = "UNCLASSIFIED";
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("line {0}: invalid coordinate {1:?}")]
+    InvalidCoordinate(usize, String),
+    #[error("line {0}: invalid level {1:?}")]
+    InvalidLevel(usize, String),
+    #[error("line {0}: invalid number {1:?}")]
+    InvalidNumber(usize, String),
+    #[error("line {0}: arc or circle without preceding V X= center")]
+    MissingCenter(usize),
+    #[error("line {0}: airspace without AN name")]
+    MissingName(usize),
+    #[error("line {0}: airspace {1} without AL lower level")]
+    MissingLowerLevel(usize, String),
+    #[error("line {0}: airspace {1} without AH upper level")]
+    MissingUpperLevel(usize, String),
+    #[error("line {0}: airspace {1} has less than three points")]
+    TooFewPoints(usize, String),
+    #[error("invalid arc resolution {0}, must be a positive number of degrees")]
+    InvalidResolution(f64),
+}
+
+#[derive(Default)]
+struct AirspaceBuilder {
+    line: usize,
+    name: Option<String>,
+    lower_level: Option<u64>,
+    upper_level: Option<u64>,
+    coords: Vec<Coord>,
+    center: Option<Point>,
+    counter_clockwise: bool,
+}
+
+impl AirspaceBuilder {
+    fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.lower_level.is_none()
+            && self.upper_level.is_none()
+            && self.coords.is_empty()
+    }
+
+    fn apply(
+        &mut self,
+        line_no: usize,
+        record: &str,
+        value: &str,
+        resolution: f64,
+    ) -> Result<(), Error> {
+        match record {
+            "AN" => self.name = Some(value.to_string()),
+            "AL" => self.lower_level = Some(parse_level(line_no, value)?),
+            "AH" => self.upper_level = Some(parse_level(line_no, value)?),
+            "V" => {
+                if let Some((key, var)) = value.split_once('=') {
+                    match key.trim().to_uppercase().as_str() {
+                        "X" => self.center = Some(parse_coord(line_no, var)?.into()),
+                        "D" => self.counter_clockwise = var.trim() == "-",
+                        _ => {}
+                    }
+                }
+            }
+            "DP" => self.coords.push(parse_coord(line_no, value)?),
+            "DC" => {
+                let center = self.center.ok_or(Error::MissingCenter(line_no))?;
+                let radius = parse_number(line_no, value)? * METERS_PER_NAUTICAL_MILE;
+                self.coords
+                    .extend(geometry::circle(center, radius, resolution));
+            }
+            "DA" => {
+                let center = self.center.ok_or(Error::MissingCenter(line_no))?;
+                let (radius, start, end) = value
+                    .split(',')
+                    .map(|n| parse_number(line_no, n))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into_iter()
+                    .collect_tuple()
+                    .ok_or_else(|| Error::InvalidNumber(line_no, value.to_string()))?;
+                self.coords.extend(geometry::arc(
+                    center,
+                    radius * METERS_PER_NAUTICAL_MILE,
+                    start,
+                    end,
+                    !self.counter_clockwise,
+                    resolution,
+                ));
+            }
+            "DB" => {
+                let center = self.center.ok_or(Error::MissingCenter(line_no))?;
+                let (from, to) = value
+                    .split_once(',')
+                    .ok_or_else(|| Error::InvalidCoordinate(line_no, value.to_string()))?;
+                let from = Point::from(parse_coord(line_no, from)?);
+                let to = Point::from(parse_coord(line_no, to)?);
+                let mut coords = geometry::arc(
+                    center,
+                    Haversine::distance(center, from),
+                    Haversine::bearing(center, from),
+                    Haversine::bearing(center, to),
+                    !self.counter_clockwise,
+                    resolution,
+                );
+                // keep the exact end points given in the file
+                if let Some(first) = coords.first_mut() {
+                    *first = from.into();
+                }
+                if let Some(last) = coords.last_mut() {
+                    *last = to.into();
+                }
+                self.coords.extend(coords);
+            }
+            // classes, labels, styling and airways are not represented in volumes
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn build(self) -> Result<(String, Volume), Error> {
+        let name = self.name.ok_or(Error::MissingName(self.line))?;
+        let lower_level = self
+            .lower_level
+            .ok_or_else(|| Error::MissingLowerLevel(self.line, name.clone()))?;
+        let upper_level = self
+            .upper_level
+            .ok_or_else(|| Error::MissingUpperLevel(self.line, name.clone()))?;
+        if self.coords.len() < 3 {
+            return Err(Error::TooFewPoints(self.line, name));
+        }
+        Ok((
+            name,
+            Volume::new(
+                lower_level,
+                upper_level,
+                Polygon::new(LineString::new(self.coords), vec![]),
+            ),
+        ))
+    }
+}
+
+/// Parses an `OpenAir` file into volumes keyed by their `AN` name, approximating arcs and circles
+/// with a point at least every `resolution` degrees.
+///
+/// Duplicate names get a numeric suffix, e.g. `TMA MUNCHEN-2`.
+pub fn import(input: &str, resolution: f64) -> Result<BTreeMap<String, Volume>, Error> {
+    if !geometry::valid_resolution(resolution) {
+        return Err(Error::InvalidResolution(resolution));
+    }
+    let mut volumes = BTreeMap::new();
+    let mut builder = AirspaceBuilder::default();
+    for (line_idx, raw_line) in input.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('*') {
+            continue;
+        }
+        let (record, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let record = record.to_uppercase();
+
+        if record == "AC" {
+            let finished = std::mem::replace(
+                &mut builder,
+                AirspaceBuilder {
+                    line: line_no,
+                    ..Default::default()
+                },
+            );
+            if !finished.is_empty() {
+                let (name, volume) = finished.build()?;
+                volume::insert_unique(&mut volumes, &name, volume);
+            }
+        } else {
+            builder.apply(line_no, &record, value.trim(), resolution)?;
+        }
+    }
+    if !builder.is_empty() {
+        let (name, volume) = builder.build()?;
+        volume::insert_unique(&mut volumes, &name, volume);
+    }
+
+    Ok(volumes)
+}
+
+/// Writes volumes as `OpenAir` airspaces, named by their id and sorted by it.
+pub fn export<'a>(volumes: impl IntoIterator<Item = (&'a String, &'a Volume)>) -> String {
+    let mut out = String::new();
+    for (id, volume) in volumes.into_iter().sorted_by_key(|(id, _)| *id) {
+        let _ = writeln!(out, "AC {EXPORT_CLASS}");
+        let _ = writeln!(out, "AN {id}");
+        let _ = writeln!(out, "AL {}", format_level(volume.lower_level()));
+        let _ = writeln!(out, "AH {}", format_level(volume.upper_level()));
+        let exterior = volume.lateral_bounds().exterior();
+        let coords = exterior.coords().collect::<Vec<_>>();
+        let open_coords = if exterior.is_closed() && coords.len() > 1 {
+            &coords[..coords.len() - 1]
+        } else {
+            &coords[..]
+        };
+        for coord in open_coords {
+            let _ = writeln!(out, "DP {}", format_coord(**coord));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn parse_number(line: usize, value: &str) -> Result<f64, Error> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| Error::InvalidNumber(line, value.to_string()))
+}
+
+/// Parses levels like `GND`, `FL95`, `3500ft AMSL`, `1000m` or `UNL` into flight levels.
+fn parse_level(line: usize, value: &str) -> Result<u64, Error> {
+    let invalid = || Error::InvalidLevel(line, value.to_string());
+    let level = value.trim().to_uppercase();
+    if level.starts_with("GND") || level.starts_with("SFC") {
+        return Ok(0);
+    }
+    if level.starts_with("UNL") {
+        return Ok(999);
+    }
+    if let Some(fl) = level.strip_prefix("FL") {
+        return fl.trim().parse().map_err(|_| invalid());
+    }
+
+    let number_end = level
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(level.len());
+    let height: f64 = level[..number_end].parse().map_err(|_| invalid())?;
+    let unit = level[number_end..].trim_start();
+    let feet = if unit.starts_with('M') && !unit.starts_with("MSL") {
+        height / METERS_PER_FOOT
+    } else {
+        height
+    };
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        reason = "parsed without sign, flight levels are small"
+    )]
+    Ok((feet / 100.0).round() as u64)
+}
+
+fn format_level(level: u64) -> String {
+    match level {
+        0 => "GND".to_string(),
+        999.. => "UNL".to_string(),
+        _ => format!("FL{level}"),
+    }
+}
+
+/// Parses coordinates like `48:30:00 N 011:20:30 E` or `48:30.5N 11:20.5E`.
+fn parse_coord(line: usize, value: &str) -> Result<Coord, Error> {
+    let invalid = || Error::InvalidCoordinate(line, value.to_string());
+    let compact = value
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+
+    let lat_end = compact.find(['N', 'S']).ok_or_else(invalid)?;
+    let (lat, rest) = compact.split_at(lat_end);
+    let (lat_hemisphere, lon) = rest.split_at(1);
+    let (lon_end, _) = lon.char_indices().next_back().ok_or_else(invalid)?;
+    let (lon, lon_hemisphere) = lon.split_at(lon_end);
+
+    let lat = parse_sexagesimal(lat).ok_or_else(invalid)?;
+    let lon = parse_sexagesimal(lon).ok_or_else(invalid)?;
+    Ok(Coord {
+        x: match lon_hemisphere {
+            "E" => lon,
+            "W" => -lon,
+            _ => return Err(invalid()),
+        },
+        y: if lat_hemisphere == "S" { -lat } else { lat },
+    })
+}
+
+fn parse_sexagesimal(value: &str) -> Option<f64> {
+    let parts = value.split(':').collect::<Vec<_>>();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    parts
+        .into_iter()
+        .zip([1.0, 60.0, 3600.0])
+        .try_fold(0.0, |acc, (part, divisor)| {
+            Some(acc + part.parse::<f64>().ok()? / divisor)
+        })
+}
+
+fn format_coord(coord: Coord) -> String {
+    fn dms(value: f64, width: usize) -> String {
+        #[allow(
+            clippy::cast_sign_loss,
+            clippy::cast_possible_truncation,
+            reason = "absolute value of a coordinate"
+        )]
+        let seconds = (value.abs() * 3600.0).round() as u32;
+        format!(
+            "{:0width$}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+
+    format!(
+        "{} {} {} {}",
+        dms(coord.y, 2),
+        if coord.y < 0.0 { 'S' } else { 'N' },
+        dms(coord.x, 3),
+        if coord.x < 0.0 { 'W' } else { 'E' },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use geo::{polygon, Contains, Point};
+
+    use crate::{Volume, DEFAULT_ARC_RESOLUTION};
+
+    const SAMPLE: &str = "
+* sample airspace
+AC D
+AN CTR TEST
+AL GND
+AH 3500ft MSL
+DP 48:00:00 N 011:00:00 E
+DP 48:00:00 N 011:30:00 E
+V X=48:00:00 N 011:15:00 E
+DB 48:00:00 N 011:30:00 E, 48:00:00 N 011:00:00 E
+
+AC C
+AN TMA TEST
+AL FL65
+AH FL195
+V X=48:15:00 N 011:15:00 E
+DC 10
+";
+
+    #[test]
+    fn test_import() {
+        let volumes = super::import(SAMPLE, DEFAULT_ARC_RESOLUTION).unwrap();
+        assert_eq!(volumes.len(), 2);
+
+        let ctr = &volumes["CTR TEST"];
+        assert_eq!(ctr.lower_level(), 0);
+        assert_eq!(ctr.upper_level(), 35);
+        // clockwise arc from east to west around the center bulges south
+        assert!(ctr.lateral_bounds().contains(&Point::new(11.15, 47.95)));
+        assert!(!ctr.lateral_bounds().contains(&Point::new(11.15, 48.05)));
+
+        let tma = &volumes["TMA TEST"];
+        assert_eq!(tma.lower_level(), 65);
+        assert_eq!(tma.upper_level(), 195);
+        assert!(tma.lateral_bounds().contains(&Point::new(11.25, 48.25)));
+        assert!(!tma.lateral_bounds().contains(&Point::new(11.25, 48.5)));
+
+        for resolution in [0.0, -5.0, f64::NAN] {
+            assert!(matches!(
+                super::import(SAMPLE, resolution),
+                Err(super::Error::InvalidResolution(_))
+            ));
+        }
+        assert!(matches!(
+            super::parse_coord(1, "48:00:00 N 011:00:00 É"),
+            Err(super::Error::InvalidCoordinate(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let volumes = BTreeMap::from([(
+            "VOL1".to_string(),
+            Volume::new(
+                95,
+                245,
+                polygon![
+                    (x: 11.5, y: 48.25),
+                    (x: 12.0, y: 48.25),
+                    (x: 12.0, y: -0.5),
+                    (x: -1.0, y: -0.5),
+                ],
+            ),
+        )]);
+
+        let openair = super::export(&volumes);
+        assert_eq!(
+            openair,
+            "AC UNCLASSIFIED
+AN VOL1
+AL FL95
+AH FL245
+DP 48:15:00 N 011:30:00 E
+DP 48:15:00 N 012:00:00 E
+DP 00:30:00 S 012:00:00 E
+DP 00:30:00 S 001:00:00 W
+
+"
+        );
+
+        let reimported = super::import(&openair, DEFAULT_ARC_RESOLUTION).unwrap();
+        assert_eq!(reimported["VOL1"].lower_level(), 95);
+        assert_eq!(
+            reimported["VOL1"].lateral_bounds(),
+            volumes["VOL1"].lateral_bounds()
+        );
+    }
+}
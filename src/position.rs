@@ -1,8 +1,11 @@
-use std::{collections::HashMap, fs::read_to_string, path::Path};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
 
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 pub enum StationType {
     #[serde(rename = "DEL")]
     ClearanceDelivery,
@@ -28,6 +31,71 @@ pub enum StationType {
     FlightServiceStation,
 }
 
+impl StationType {
+    pub const ALL: [Self; 11] = [
+        Self::ClearanceDelivery,
+        Self::Ramp,
+        Self::Radio,
+        Self::TrafficManagement,
+        Self::FlowManagement,
+        Self::Ground,
+        Self::Tower,
+        Self::Approach,
+        Self::Departure,
+        Self::Center,
+        Self::FlightServiceStation,
+    ];
+
+    /// Callsign suffix as used on the network, e.g. `CTR`
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ClearanceDelivery => "DEL",
+            Self::Ramp => "RMP",
+            Self::Radio => "RDO",
+            Self::TrafficManagement => "TMU",
+            Self::FlowManagement => "FMP",
+            Self::Ground => "GND",
+            Self::Tower => "TWR",
+            Self::Approach => "APP",
+            Self::Departure => "DEP",
+            Self::Center => "CTR",
+            Self::FlightServiceStation => "FSS",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("unknown station type {0:?}")]
+pub struct UnknownStationType(pub String);
+
+impl FromStr for StationType {
+    type Err = UnknownStationType;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "DEL" => Self::ClearanceDelivery,
+            "RMP" => Self::Ramp,
+            "RDO" => Self::Radio,
+            "TMU" => Self::TrafficManagement,
+            "FMP" => Self::FlowManagement,
+            "GND" => Self::Ground,
+            "TWR" => Self::Tower,
+            "APP" => Self::Approach,
+            "DEP" => Self::Departure,
+            "CTR" => Self::Center,
+            "FSS" => Self::FlightServiceStation,
+            _ => return Err(UnknownStationType(s.to_string())),
+        })
+    }
+}
+
+impl fmt::Display for StationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "type", content = "group", rename_all = "lowercase")]
 pub enum GcapTier {
@@ -53,8 +121,19 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn from_toml(path: &Path) -> Result<HashMap<String, Self>, super::Error> {
-        Ok(toml::from_str(&read_to_string(path)?)?)
+    #[cfg(feature = "fs")]
+    pub fn from_toml(path: &Path) -> Result<BTreeMap<String, Self>, super::Error> {
+        Self::from_toml_str(&read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<BTreeMap<String, Self>, super::Error> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Callsign without middle letters, e.g. `EDDM_APP`
+    #[must_use]
+    pub fn callsign(&self) -> String {
+        format!("{}_{}", self.prefix, self.station_type)
     }
 }
 
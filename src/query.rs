@@ -0,0 +1,116 @@
+use geo::{Contains, Point};
+use itertools::Itertools;
+
+use crate::{FirName, OpenData, Position, PositionId, Sector, SectorId, Volume, VolumeId};
+
+impl OpenData {
+    /// Volumes laterally containing `point` and, if given, vertically containing flight level
+    /// `level`, sorted by FIR and id.
+    #[must_use]
+    pub fn volumes_at(
+        &self,
+        point: Point,
+        level: Option<u64>,
+    ) -> Vec<(&FirName, &VolumeId, &Volume)> {
+        self.firs
+            .iter()
+            .flat_map(|(fir_name, fir)| {
+                fir.volumes
+                    .iter()
+                    .map(move |(volume_id, volume)| (fir_name, volume_id, volume))
+            })
+            .filter(|(_, _, volume)| {
+                level.is_none_or(|level| {
+                    volume.lower_level() <= level && level < volume.upper_level()
+                }) && volume.lateral_bounds().contains(&point)
+            })
+            .sorted_by_key(|(fir_name, volume_id, _)| (*fir_name, *volume_id))
+            .collect()
+    }
+
+    /// Sectors with at least one volume at `point` and `level`, see [`OpenData::volumes_at`].
+    #[must_use]
+    pub fn sectors_at(
+        &self,
+        point: Point,
+        level: Option<u64>,
+    ) -> Vec<(&FirName, &SectorId, &Sector)> {
+        let volumes = self.volumes_at(point, level);
+        self.sectors()
+            .filter(|(fir_name, _, sector)| {
+                volumes.iter().any(|(volume_fir, volume_id, _)| {
+                    volume_fir == fir_name && sector.volumes.contains(volume_id)
+                })
+            })
+            .sorted_by_key(|(fir_name, sector_id, _)| (*fir_name, *sector_id))
+            .collect()
+    }
+
+    /// Positions a station logging in as `callsign` (e.g. `EDDM_N_APP`) matches, i.e. with the
+    /// same station type and a prefix the callsign starts with, most specific prefix first.
+    #[must_use]
+    pub fn match_callsign(&self, callsign: &str) -> Vec<(&FirName, &PositionId, &Position)> {
+        let callsign = callsign.to_uppercase();
+        let mut parts = callsign.split('_');
+        let (Some(prefix), Some(Ok(station_type))) =
+            (parts.next(), parts.next_back().map(str::parse))
+        else {
+            return vec![];
+        };
+
+        self.positions()
+            .filter(|(_, _, position)| {
+                position.station_type == station_type && prefix.starts_with(&position.prefix)
+            })
+            .sorted_by(|(fir_a, id_a, a), (fir_b, id_b, b)| {
+                b.prefix
+                    .len()
+                    .cmp(&a.prefix.len())
+                    .then_with(|| (fir_a, id_a).cmp(&(fir_b, id_b)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geo::Point;
+
+    use crate::{position::StationType, OpenData};
+
+    fn ids<T>(matches: Vec<(&String, &String, T)>) -> Vec<String> {
+        matches.into_iter().map(|(_, id, _)| id.clone()).collect()
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut open_data = OpenData::default();
+        open_data
+            .firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        let mut general = open_data.firs["EDMM"].positions["MMR"].clone();
+        general.prefix = "ED".to_string();
+        let fir = open_data.firs.get_mut("EDMM").unwrap();
+        fir.positions.insert("AAA".to_string(), general);
+
+        assert_eq!(ids(open_data.match_callsign("edmm_n_ctr")), ["MMR", "AAA"]);
+        assert_eq!(ids(open_data.match_callsign("EDGG_CTR")), ["AAA"]);
+        assert!(open_data.match_callsign("LOVV_CTR").is_empty());
+        assert!(open_data.match_callsign("EDMM_APP").is_empty());
+        assert!(open_data.match_callsign("EDMM").is_empty());
+        assert!(open_data.match_callsign("EDMM_XYZ").is_empty());
+        assert_eq!(
+            open_data.match_callsign("EDMM_CTR")[0].2.station_type,
+            StationType::Center
+        );
+
+        let inside = Point::new(11.8, 48.2);
+        assert_eq!(ids(open_data.volumes_at(inside, None)), ["TEST"]);
+        assert_eq!(ids(open_data.volumes_at(inside, Some(0))), ["TEST"]);
+        assert!(open_data.volumes_at(inside, Some(245)).is_empty());
+        assert!(open_data
+            .volumes_at(Point::new(11.2, 48.8), None)
+            .is_empty());
+        assert_eq!(ids(open_data.sectors_at(inside, Some(100))), ["TEST"]);
+    }
+}
@@ -0,0 +1,390 @@
+//! Offline SVG rendering of a FIR's sectorisation, optionally rasterized to PNG.
+
+use std::fmt::Write;
+
+use geo::{BoundingRect, Centroid, Coord, LineString, Rect};
+use itertools::Itertools;
+use thiserror::Error;
+
+use crate::{OpenData, Volume, FIR};
+
+const MARGIN: f64 = 20.0;
+const PALETTE: [&str; 8] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unknown FIR {0}")]
+    UnknownFir(String),
+    #[error("nothing to render for FIR {0}")]
+    Empty(String),
+    #[cfg(feature = "png")]
+    #[error("could not parse rendered SVG: {0}")]
+    ParseSvg(#[from] resvg::usvg::Error),
+    #[cfg(feature = "png")]
+    #[error("could not encode PNG: {0}")]
+    EncodePng(String),
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Projection {
+    #[default]
+    Equirectangular,
+    Mercator,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub projection: Projection,
+    /// Only draw volumes covering this flight level, otherwise every level band is a layer
+    pub level: Option<u64>,
+    /// Width of the image in pixels, the height follows from the projected extent
+    pub width: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            projection: Projection::default(),
+            level: None,
+            width: 1000.0,
+        }
+    }
+}
+
+struct Transform {
+    projection: Projection,
+    /// cosine of the center latitude, scaling longitudes for equirectangular projection
+    lon_scale: f64,
+    bounds: Rect,
+    scale: f64,
+}
+
+impl Transform {
+    fn project(projection: Projection, lon_scale: f64, coord: Coord) -> Coord {
+        match projection {
+            Projection::Equirectangular => Coord {
+                x: coord.x * lon_scale,
+                y: coord.y,
+            },
+            Projection::Mercator => Coord {
+                x: coord.x,
+                y: (std::f64::consts::FRAC_PI_4 + coord.y.to_radians() / 2.0)
+                    .tan()
+                    .ln()
+                    .to_degrees(),
+            },
+        }
+    }
+
+    /// Projected coordinate in image pixels, north up
+    fn apply(&self, coord: Coord) -> Coord {
+        let projected = Self::project(self.projection, self.lon_scale, coord);
+        Coord {
+            x: MARGIN + (projected.x - self.bounds.min().x) * self.scale,
+            y: MARGIN + (self.bounds.max().y - projected.y) * self.scale,
+        }
+    }
+
+    fn height(&self) -> f64 {
+        self.bounds.height() * self.scale + 2.0 * MARGIN
+    }
+}
+
+/// Renders the volumes of `fir_name` with sector labels, owning callsigns and airports.
+pub fn svg(open_data: &OpenData, fir_name: &str, options: &Options) -> Result<String, Error> {
+    let fir = open_data
+        .firs
+        .get(fir_name)
+        .ok_or_else(|| Error::UnknownFir(fir_name.to_string()))?;
+
+    let volumes = fir
+        .volumes
+        .iter()
+        .filter(|(_, volume)| {
+            options
+                .level
+                .is_none_or(|level| volume.lower_level() <= level && level < volume.upper_level())
+        })
+        .sorted_by_key(|(id, volume)| (volume.lower_level(), volume.upper_level(), *id))
+        .collect::<Vec<_>>();
+
+    let transform =
+        transform(fir, &volumes, options).ok_or_else(|| Error::Empty(fir_name.to_string()))?;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" font-family="sans-serif" font-size="11">"#,
+        options.width,
+        transform.height()
+    );
+    let title = match fir
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.name.as_ref())
+    {
+        Some(name) => format!("{name} ({fir_name})"),
+        None => fir_name.to_string(),
+    };
+    let _ = writeln!(out, "<title>{}</title>", escape(&title));
+
+    let sorted_sectors = fir.sectors.keys().sorted().collect::<Vec<_>>();
+    for ((lower, upper), layer) in &volumes
+        .iter()
+        .chunk_by(|(_, volume)| (volume.lower_level(), volume.upper_level()))
+    {
+        let _ = writeln!(out, r#"<g id="FL{lower:03}-{upper:03}" class="layer">"#);
+        for (volume_id, volume) in layer {
+            let sector_idx = sorted_sectors
+                .iter()
+                .position(|sector_id| fir.sectors[*sector_id].volumes.contains(volume_id));
+            let color = sector_idx.map_or("#999999", |idx| PALETTE[idx % PALETTE.len()]);
+            let _ = writeln!(
+                out,
+                r#"<path id="{}" d="{}" fill="{color}" fill-opacity="0.3" stroke="{color}" fill-rule="evenodd"/>"#,
+                escape(volume_id),
+                path(&transform, volume),
+            );
+            if let Some(sector_id) = sector_idx.map(|idx| sorted_sectors[idx]) {
+                label(&mut out, &transform, open_data, fir_name, sector_id, volume);
+            }
+        }
+        out.push_str("</g>\n");
+    }
+
+    out.push_str("<g class=\"airports\">\n");
+    for (icao, airport) in fir.airports.iter().sorted_by_key(|(icao, _)| *icao) {
+        let point = transform.apply(airport.location.0);
+        let _ = writeln!(
+            out,
+            r#"<circle cx="{:.1}" cy="{:.1}" r="3" fill="black"/><text x="{:.1}" y="{:.1}">{}</text>"#,
+            point.x,
+            point.y,
+            point.x + 5.0,
+            point.y - 5.0,
+            escape(icao)
+        );
+    }
+    out.push_str("</g>\n</svg>\n");
+
+    Ok(out)
+}
+
+/// Renders like [`svg`] and rasterizes the result, text in the system fonts.
+#[cfg(feature = "png")]
+pub fn png(open_data: &OpenData, fir_name: &str, options: &Options) -> Result<Vec<u8>, Error> {
+    use resvg::{tiny_skia, usvg};
+
+    let svg = svg(open_data, fir_name, options)?;
+    let mut usvg_options = usvg::Options::default();
+    usvg_options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(&svg, &usvg_options)?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| Error::Empty(fir_name.to_string()))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|e| Error::EncodePng(e.to_string()))
+}
+
+fn transform(fir: &FIR, volumes: &[(&String, &Volume)], options: &Options) -> Option<Transform> {
+    let coords = volumes
+        .iter()
+        .flat_map(|(_, volume)| volume.lateral_bounds().exterior().coords().copied())
+        .chain(fir.airports.values().map(|airport| airport.location.0))
+        .collect::<LineString>();
+    let geographic = coords.bounding_rect()?;
+    let lon_scale = geographic.center().y.to_radians().cos();
+
+    let bounds = coords
+        .coords()
+        .map(|coord| Transform::project(options.projection, lon_scale, *coord))
+        .collect::<LineString>()
+        .bounding_rect()?;
+    let extent = bounds.width().max(bounds.height());
+    if extent <= 0.0 {
+        return None;
+    }
+
+    Some(Transform {
+        projection: options.projection,
+        lon_scale,
+        bounds,
+        scale: (options.width - 2.0 * MARGIN) / extent,
+    })
+}
+
+fn path(transform: &Transform, volume: &Volume) -> String {
+    std::iter::once(volume.lateral_bounds().exterior())
+        .chain(volume.lateral_bounds().interiors())
+        .map(|ring| {
+            let points = ring
+                .coords()
+                .map(|coord| {
+                    let point = transform.apply(*coord);
+                    format!("{:.1},{:.1}", point.x, point.y)
+                })
+                .join(" L");
+            format!("M{points} Z")
+        })
+        .join(" ")
+}
+
+fn label(
+    out: &mut String,
+    transform: &Transform,
+    open_data: &OpenData,
+    fir_name: &str,
+    sector_id: &str,
+    volume: &Volume,
+) {
+    let Some(centroid) = volume.lateral_bounds().centroid() else {
+        return;
+    };
+    let point = transform.apply(centroid.0);
+    let owner = open_data.firs[fir_name].sectors[sector_id]
+        .position_priority
+        .iter()
+        .flatten()
+        .find_map(|pos_ref| open_data.resolve(fir_name, pos_ref))
+        .map(crate::Position::callsign);
+
+    let _ = write!(
+        out,
+        r#"<text x="{:.1}" y="{:.1}" text-anchor="middle">{}"#,
+        point.x,
+        point.y,
+        escape(sector_id)
+    );
+    if let Some(owner) = owner {
+        let _ = write!(
+            out,
+            r#"<tspan x="{:.1}" dy="1.2em">{}</tspan>"#,
+            point.x,
+            escape(&owner)
+        );
+    }
+    out.push_str("</text>\n");
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use geo::{point, polygon};
+
+    use crate::{
+        position::{PositionReference, StationType},
+        Airport, FirMetadata, OpenData, Position, Sector, Volume, FIR,
+    };
+
+    #[test]
+    fn test_svg() {
+        let open_data = OpenData {
+            firs: BTreeMap::from([(
+                "EDMM".to_string(),
+                FIR {
+                    positions: BTreeMap::from([(
+                        "MMR".to_string(),
+                        Position {
+                            frequency: 129_100_000,
+                            prefix: "EDMM".to_string(),
+                            station_type: StationType::Center,
+                            radio_callsign: "München Radar".to_string(),
+                            name: None,
+                            cpdlc_logon: None,
+                            airspace_groups: vec![],
+                            gcap_tier: None,
+                        },
+                    )]),
+                    volumes: BTreeMap::from([
+                        (
+                            "LOW".to_string(),
+                            Volume::new(
+                                0,
+                                245,
+                                polygon![(x: 11.0, y: 48.0), (x: 12.0, y: 48.0), (x: 12.0, y: 49.0)],
+                            ),
+                        ),
+                        (
+                            "HIGH".to_string(),
+                            Volume::new(
+                                245,
+                                660,
+                                polygon![(x: 11.0, y: 48.0), (x: 12.0, y: 48.0), (x: 12.0, y: 49.0)],
+                            ),
+                        ),
+                    ]),
+                    sectors: BTreeMap::from([(
+                        "MUC<LOW>".to_string(),
+                        Sector {
+                            name: None,
+                            volumes: vec!["LOW".to_string()],
+                            runway_filter: vec![],
+                            position_priority: vec![vec![PositionReference {
+                                fir: None,
+                                id: "MMR".to_string(),
+                            }]],
+                        },
+                    )]),
+                    airports: BTreeMap::from([(
+                        "EDDM".to_string(),
+                        Airport {
+                            name: "München".to_string(),
+                            iata_designator: None,
+                            fallback_prefixes: vec![],
+                            location: point!(x: 11.78, y: 48.35),
+                            elevation: None,
+                            position_priority: vec![],
+                            runways: vec![],
+                        },
+                    )]),
+                    metadata: Some(FirMetadata {
+                        name: Some("München".to_string()),
+                        ..Default::default()
+                    }),
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let all = super::svg(&open_data, "EDMM", &super::Options::default()).unwrap();
+        assert!(all.contains("<title>München (EDMM)</title>"));
+        assert!(all.contains(r#"<g id="FL000-245" class="layer">"#));
+        assert!(all.contains(r#"<g id="FL245-660" class="layer">"#));
+        assert!(all.contains("MUC&lt;LOW&gt;"));
+        assert!(all.contains("EDMM_CTR"));
+        assert!(all.contains(">EDDM</text>"));
+
+        let high = super::svg(
+            &open_data,
+            "EDMM",
+            &super::Options {
+                level: Some(300),
+                projection: super::Projection::Mercator,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(!high.contains("FL000-245"));
+        assert!(high.contains(r#"<path id="HIGH""#));
+
+        assert!(super::svg(&open_data, "EDGG", &super::Options::default()).is_err());
+
+        #[cfg(feature = "png")]
+        {
+            let png = super::png(&open_data, "EDMM", &super::Options::default()).unwrap();
+            assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        }
+    }
+}
@@ -1,4 +1,6 @@
-use std::{collections::HashMap, fs::read_to_string, path::Path};
+use std::collections::BTreeMap;
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
 
 use serde::{Deserialize, Serialize};
 
@@ -16,7 +18,12 @@ pub struct Sector {
 }
 
 impl Sector {
-    pub fn from_toml(path: &Path) -> Result<HashMap<String, Self>, super::Error> {
-        Ok(toml::from_str(&read_to_string(path)?)?)
+    #[cfg(feature = "fs")]
+    pub fn from_toml(path: &Path) -> Result<BTreeMap<String, Self>, super::Error> {
+        Self::from_toml_str(&read_to_string(path)?)
+    }
+
+    pub fn from_toml_str(contents: &str) -> Result<BTreeMap<String, Self>, super::Error> {
+        Ok(toml::from_str(contents)?)
     }
 }
@@ -0,0 +1,211 @@
+//! Read-only JSON API over the data set for tools not written in Rust.
+//!
+//! | Endpoint | Response |
+//! |---|---|
+//! | `GET /health` | status, crate version and number of FIRs |
+//! | `GET /firs` | FIR names |
+//! | `GET /firs/{fir}` | whole FIR |
+//! | `GET /firs/{fir}/{positions,sectors,airports,volumes}` | entities by id |
+//! | `GET /firs/{fir}/{positions,sectors,airports,volumes}/{id}` | single entity |
+//! | `GET /uirs` | UIR names with their member FIRs |
+//! | `GET /uirs/{uir}` | member FIRs and the lateral boundary as `GeoJSON` geometry |
+//! | `GET /point?lat=..&lon=..[&level=..]` | volumes and sectors at a location |
+//! | `GET /callsign/{callsign}` | matching positions, most specific first |
+
+use std::{
+    collections::BTreeMap,
+    io,
+    sync::{PoisonError, RwLock},
+};
+
+use geo::Point;
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+use tracing::{info, warn};
+
+use crate::OpenData;
+
+/// Answers requests on `server` from the current state of `open_data`, which may be replaced
+/// concurrently to reload the data set. Only returns when the server is unblocked.
+pub fn serve(server: &Server, open_data: &RwLock<OpenData>) {
+    for request in server.incoming_requests() {
+        let (status, body) = {
+            let open_data = open_data.read().unwrap_or_else(PoisonError::into_inner);
+            respond(&open_data, request.method(), request.url())
+        };
+        info!("{} {} {status}", request.method(), request.url());
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/json")
+                    .expect("static header is valid"),
+            );
+        if let Err(e) = request.respond(response) {
+            warn!("could not send response: {e}");
+        }
+    }
+}
+
+/// Status code and JSON body for a request to `url`
+#[must_use]
+pub fn respond(open_data: &OpenData, method: &Method, url: &str) -> (u16, Value) {
+    if *method != Method::Get {
+        return error(405, "only GET is supported");
+    }
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments = path.split('/').filter(|s| !s.is_empty()).collect_vec();
+    match segments[..] {
+        ["health"] => (
+            200,
+            json!({
+                "status": "ok",
+                "version": env!("CARGO_PKG_VERSION"),
+                "firs": open_data.firs.len(),
+            }),
+        ),
+        ["firs"] => (200, json!(open_data.firs.keys().collect_vec())),
+        ["firs", fir_name, ref rest @ ..] => {
+            let Some(fir) = open_data.firs.get(fir_name) else {
+                return error(404, &format!("unknown FIR {fir_name}"));
+            };
+            match rest {
+                [] => to_response(fir),
+                ["positions", ref id @ ..] => entities(&fir.positions, id),
+                ["sectors", ref id @ ..] => entities(&fir.sectors, id),
+                ["airports", ref id @ ..] => entities(&fir.airports, id),
+                ["volumes", ref id @ ..] => entities(&fir.volumes, id),
+                _ => error(404, "not found"),
+            }
+        }
+        ["uirs"] => (200, json!(open_data.uirs())),
+        ["uirs", uir] => {
+            let boundary = open_data.uir_boundary(uir);
+            if boundary.0.is_empty() && !open_data.uirs().contains_key(uir) {
+                return error(404, &format!("unknown UIR {uir}"));
+            }
+            (
+                200,
+                json!({
+                    "firs": open_data.uirs().get(uir),
+                    "boundary": geojson::Geometry::from(&boundary),
+                }),
+            )
+        }
+        ["point"] => point(open_data, query),
+        ["callsign", callsign] => (
+            200,
+            open_data
+                .match_callsign(callsign)
+                .into_iter()
+                .map(|(fir, id, position)| json!({ "fir": fir, "id": id, "position": position }))
+                .collect(),
+        ),
+        _ => error(404, "not found"),
+    }
+}
+
+fn point(open_data: &OpenData, query: &str) -> (u16, Value) {
+    let params = query
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .collect::<BTreeMap<_, _>>();
+    let number = |name| {
+        params
+            .get(name)
+            .map(|value| value.parse::<f64>())
+            .transpose()
+    };
+    let (Ok(Some(lat)), Ok(Some(lon)), Ok(level)) = (
+        number("lat"),
+        number("lon"),
+        params.get("level").map(|l| l.parse()).transpose(),
+    ) else {
+        return error(
+            400,
+            "expected numeric lat and lon and optionally an integer level",
+        );
+    };
+    let point = Point::new(lon, lat);
+    (
+        200,
+        json!({
+            "volumes": open_data
+                .volumes_at(point, level)
+                .into_iter()
+                .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+                .collect_vec(),
+            "sectors": open_data
+                .sectors_at(point, level)
+                .into_iter()
+                .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+                .collect_vec(),
+        }),
+    )
+}
+
+fn entities<T: Serialize>(entities: &BTreeMap<String, T>, id: &[&str]) -> (u16, Value) {
+    match id {
+        [] => to_response(entities),
+        [id] => match entities.get(*id) {
+            Some(entity) => to_response(entity),
+            None => error(404, &format!("unknown id {id}")),
+        },
+        _ => error(404, "not found"),
+    }
+}
+
+fn to_response(value: impl Serialize) -> (u16, Value) {
+    match serde_json::to_value(value) {
+        Ok(value) => (200, value),
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+/// Binds a server to `address`, e.g. `127.0.0.1:8080`
+pub fn bind(address: &str) -> io::Result<Server> {
+    Server::http(address).map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_http::Method;
+
+    use crate::OpenData;
+
+    #[test]
+    fn test_respond() {
+        let mut open_data = OpenData::default();
+        open_data
+            .firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        let get = |url: &str| super::respond(&open_data, &Method::Get, url);
+
+        assert_eq!(get("/health").0, 200);
+        assert_eq!(get("/firs").1, serde_json::json!(["EDMM"]));
+        let (status, positions) = get("/firs/EDMM/positions");
+        assert_eq!(status, 200);
+        let (id, position) = positions.as_object().unwrap().iter().next().unwrap();
+        assert_eq!(get(&format!("/firs/EDMM/positions/{id}")).1, *position);
+        assert_eq!(get("/firs/EDXX").0, 404);
+        assert_eq!(get("/uirs").1, serde_json::json!({}));
+        assert_eq!(get("/uirs/EDUU").0, 404);
+        assert_eq!(get("/firs/EDMM/positions/XXX").0, 404);
+        assert_eq!(get("/point?lat=48&lon=x").0, 400);
+        assert_eq!(
+            get("/point?lat=48.2&lon=11.8&level=100").1["sectors"],
+            serde_json::json!([{ "fir": "EDMM", "id": "TEST" }])
+        );
+        assert_eq!(
+            get("/point?lat=48.2&lon=11.8&level=300").1["sectors"],
+            serde_json::json!([])
+        );
+        assert_eq!(get("/callsign/EDMM_CTR").1[0]["id"], *id);
+        assert_eq!(super::respond(&open_data, &Method::Post, "/firs").0, 405);
+    }
+}
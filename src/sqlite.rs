@@ -0,0 +1,495 @@
+//! Export of [`OpenData`] into a normalized `SQLite` database for ad-hoc querying.
+//!
+//! Position references are stored fully qualified: a reference without an explicit FIR points
+//! into the FIR of the referencing entity, the same way [`OpenData::run_checks`] resolves them.
+//! Foreign keys are declared but not enforced on export, run `PRAGMA foreign_key_check` to find
+//! dangling references.
+
+use std::{fs, io, path::Path};
+
+use geo::{LineString, Point, Polygon};
+use geojson::Geometry;
+use itertools::Itertools;
+use rusqlite::{params, Connection, Transaction};
+use thiserror::Error;
+
+use crate::{position::GcapTier, OpenData};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("failed to remove existing database: {0}")]
+    Remove(#[from] io::Error),
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+const SCHEMA: &str = "
+CREATE TABLE firs (
+    name TEXT PRIMARY KEY NOT NULL,
+    full_name TEXT,
+    icao TEXT,
+    uir TEXT,
+    country TEXT,
+    vacc TEXT,
+    transition_altitude INTEGER,
+    vateud8_region INTEGER
+);
+
+CREATE TABLE fir_neighbours (
+    fir TEXT NOT NULL REFERENCES firs (name),
+    neighbour TEXT NOT NULL REFERENCES firs (name)
+);
+
+CREATE TABLE positions (
+    fir TEXT NOT NULL REFERENCES firs (name),
+    id TEXT NOT NULL,
+    frequency INTEGER NOT NULL,
+    prefix TEXT NOT NULL,
+    station_type TEXT NOT NULL,
+    name TEXT,
+    radio_callsign TEXT NOT NULL,
+    cpdlc_logon TEXT,
+    gcap_tier INTEGER,
+    gcap_group TEXT,
+    PRIMARY KEY (fir, id)
+);
+
+CREATE TABLE position_airspace_groups (
+    fir TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    airspace_group TEXT NOT NULL,
+    FOREIGN KEY (fir, position_id) REFERENCES positions (fir, id)
+);
+
+CREATE TABLE volumes (
+    fir TEXT NOT NULL REFERENCES firs (name),
+    id TEXT NOT NULL,
+    lower_level INTEGER NOT NULL,
+    upper_level INTEGER NOT NULL,
+    wkt TEXT NOT NULL,
+    geojson TEXT NOT NULL,
+    PRIMARY KEY (fir, id)
+);
+
+CREATE TABLE sectors (
+    fir TEXT NOT NULL REFERENCES firs (name),
+    id TEXT NOT NULL,
+    name TEXT,
+    PRIMARY KEY (fir, id)
+);
+
+CREATE TABLE sector_volumes (
+    fir TEXT NOT NULL,
+    sector_id TEXT NOT NULL,
+    volume_id TEXT NOT NULL,
+    FOREIGN KEY (fir, sector_id) REFERENCES sectors (fir, id),
+    FOREIGN KEY (fir, volume_id) REFERENCES volumes (fir, id)
+);
+
+CREATE TABLE sector_priorities (
+    fir TEXT NOT NULL,
+    sector_id TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    position_fir TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    FOREIGN KEY (fir, sector_id) REFERENCES sectors (fir, id),
+    FOREIGN KEY (position_fir, position_id) REFERENCES positions (fir, id)
+);
+
+CREATE TABLE sector_runway_filters (
+    fir TEXT NOT NULL,
+    sector_id TEXT NOT NULL,
+    filter_group INTEGER NOT NULL,
+    airport_icao TEXT NOT NULL,
+    runway TEXT NOT NULL,
+    FOREIGN KEY (fir, sector_id) REFERENCES sectors (fir, id)
+);
+
+CREATE TABLE airports (
+    fir TEXT NOT NULL REFERENCES firs (name),
+    icao TEXT NOT NULL,
+    name TEXT NOT NULL,
+    iata_designator TEXT,
+    longitude REAL NOT NULL,
+    latitude REAL NOT NULL,
+    elevation INTEGER,
+    PRIMARY KEY (fir, icao)
+);
+
+CREATE TABLE airport_fallback_prefixes (
+    fir TEXT NOT NULL,
+    airport_icao TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    FOREIGN KEY (fir, airport_icao) REFERENCES airports (fir, icao)
+);
+
+CREATE TABLE airport_priorities (
+    fir TEXT NOT NULL,
+    airport_icao TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    position_fir TEXT NOT NULL,
+    position_id TEXT NOT NULL,
+    FOREIGN KEY (fir, airport_icao) REFERENCES airports (fir, icao),
+    FOREIGN KEY (position_fir, position_id) REFERENCES positions (fir, id)
+);
+
+CREATE TABLE runways (
+    fir TEXT NOT NULL,
+    airport_icao TEXT NOT NULL,
+    designator TEXT NOT NULL,
+    reciprocal TEXT,
+    threshold_longitude REAL,
+    threshold_latitude REAL,
+    reciprocal_threshold_longitude REAL,
+    reciprocal_threshold_latitude REAL,
+    true_heading REAL,
+    length REAL,
+    elevation INTEGER,
+    FOREIGN KEY (fir, airport_icao) REFERENCES airports (fir, icao)
+);
+";
+
+/// Writes `open_data` into a new `SQLite` database at `path`, replacing an existing file.
+pub fn export(open_data: &OpenData, path: &Path) -> Result<(), Error> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    let mut conn = Connection::open(path)?;
+    export_to_connection(open_data, &mut conn)
+}
+
+/// Creates the schema in an empty database and inserts all of `open_data`.
+pub fn export_to_connection(open_data: &OpenData, conn: &mut Connection) -> Result<(), Error> {
+    // dangling position references are a check failure, not an export failure
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let tx = conn.transaction()?;
+    tx.execute_batch(SCHEMA)?;
+    for (fir_name, fir) in &open_data.firs {
+        let vateud8_region = open_data
+            .config
+            .firs
+            .get(fir_name)
+            .and_then(|fir_config| fir_config.vateud8_region);
+        insert_fir(&tx, fir_name, fir, vateud8_region)?;
+        insert_positions(&tx, fir_name, fir)?;
+        insert_volumes(&tx, fir_name, fir)?;
+        insert_sectors(&tx, fir_name, fir)?;
+        insert_airports(&tx, fir_name, fir)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn insert_fir(
+    tx: &Transaction,
+    fir_name: &str,
+    fir: &crate::FIR,
+    vateud8_region: Option<u32>,
+) -> Result<(), Error> {
+    let metadata = fir.metadata.clone().unwrap_or_default();
+    tx.execute(
+        "INSERT INTO firs \
+         (name, full_name, icao, uir, country, vacc, transition_altitude, vateud8_region) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            fir_name,
+            metadata.name,
+            metadata.icao,
+            metadata.uir,
+            metadata.country,
+            metadata.vacc,
+            metadata.transition_altitude,
+            vateud8_region,
+        ],
+    )?;
+    let mut stmt = tx.prepare("INSERT INTO fir_neighbours (fir, neighbour) VALUES (?1, ?2)")?;
+    for neighbour in &metadata.neighbours {
+        stmt.execute(params![fir_name, neighbour])?;
+    }
+    Ok(())
+}
+
+fn insert_positions(tx: &Transaction, fir_name: &str, fir: &crate::FIR) -> Result<(), Error> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO positions (fir, id, frequency, prefix, station_type, name, radio_callsign, \
+         cpdlc_logon, gcap_tier, gcap_group) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut group_stmt = tx.prepare(
+        "INSERT INTO position_airspace_groups (fir, position_id, airspace_group) \
+         VALUES (?1, ?2, ?3)",
+    )?;
+    for (id, position) in &fir.positions {
+        let (gcap_tier, gcap_group) = match &position.gcap_tier {
+            Some(GcapTier::One) => (Some(1), None),
+            Some(GcapTier::Two(group)) => (Some(2), Some(group)),
+            None => (None, None),
+        };
+        stmt.execute(params![
+            fir_name,
+            id,
+            position.frequency,
+            position.prefix,
+            position.station_type.as_str(),
+            position.name,
+            position.radio_callsign,
+            position.cpdlc_logon,
+            gcap_tier,
+            gcap_group,
+        ])?;
+        for group in &position.airspace_groups {
+            group_stmt.execute(params![fir_name, id, group])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_volumes(tx: &Transaction, fir_name: &str, fir: &crate::FIR) -> Result<(), Error> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO volumes (fir, id, lower_level, upper_level, wkt, geojson) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for (id, volume) in &fir.volumes {
+        stmt.execute(params![
+            fir_name,
+            id,
+            volume.lower_level(),
+            volume.upper_level(),
+            polygon_wkt(volume.lateral_bounds()),
+            Geometry::from(volume.lateral_bounds()).to_string(),
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_sectors(tx: &Transaction, fir_name: &str, fir: &crate::FIR) -> Result<(), Error> {
+    let mut stmt = tx.prepare("INSERT INTO sectors (fir, id, name) VALUES (?1, ?2, ?3)")?;
+    let mut volume_stmt =
+        tx.prepare("INSERT INTO sector_volumes (fir, sector_id, volume_id) VALUES (?1, ?2, ?3)")?;
+    let mut priority_stmt = tx.prepare(
+        "INSERT INTO sector_priorities (fir, sector_id, priority, position_fir, position_id) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut runway_stmt = tx.prepare(
+        "INSERT INTO sector_runway_filters (fir, sector_id, filter_group, airport_icao, runway) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for (id, sector) in &fir.sectors {
+        stmt.execute(params![fir_name, id, sector.name])?;
+        for volume in &sector.volumes {
+            volume_stmt.execute(params![fir_name, id, volume])?;
+        }
+        for (priority, pos_ref) in sector
+            .position_priority
+            .iter()
+            .enumerate()
+            .flat_map(|(priority, refs)| refs.iter().map(move |pos_ref| (priority, pos_ref)))
+        {
+            priority_stmt.execute(params![
+                fir_name,
+                id,
+                priority,
+                pos_ref.fir.as_deref().unwrap_or(fir_name),
+                pos_ref.id,
+            ])?;
+        }
+        for (group, runway) in sector
+            .runway_filter
+            .iter()
+            .enumerate()
+            .flat_map(|(group, runways)| runways.iter().map(move |runway| (group, runway)))
+        {
+            runway_stmt.execute(params![fir_name, id, group, runway.icao, runway.designator])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_airports(tx: &Transaction, fir_name: &str, fir: &crate::FIR) -> Result<(), Error> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO airports (fir, icao, name, iata_designator, longitude, latitude, elevation) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    let mut prefix_stmt = tx.prepare(
+        "INSERT INTO airport_fallback_prefixes (fir, airport_icao, prefix) VALUES (?1, ?2, ?3)",
+    )?;
+    let mut priority_stmt = tx.prepare(
+        "INSERT INTO airport_priorities (fir, airport_icao, priority, position_fir, position_id) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut runway_stmt = tx.prepare(
+        "INSERT INTO runways (fir, airport_icao, designator, reciprocal, threshold_longitude, \
+         threshold_latitude, reciprocal_threshold_longitude, reciprocal_threshold_latitude, \
+         true_heading, length, elevation) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    for (icao, airport) in &fir.airports {
+        stmt.execute(params![
+            fir_name,
+            icao,
+            airport.name,
+            airport.iata_designator,
+            airport.location.x(),
+            airport.location.y(),
+            airport.elevation,
+        ])?;
+        for prefix in &airport.fallback_prefixes {
+            prefix_stmt.execute(params![fir_name, icao, prefix])?;
+        }
+        for (priority, pos_ref) in airport
+            .position_priority
+            .iter()
+            .enumerate()
+            .flat_map(|(priority, refs)| refs.iter().map(move |pos_ref| (priority, pos_ref)))
+        {
+            priority_stmt.execute(params![
+                fir_name,
+                icao,
+                priority,
+                pos_ref.fir.as_deref().unwrap_or(fir_name),
+                pos_ref.id,
+            ])?;
+        }
+        for runway in &airport.runways {
+            runway_stmt.execute(params![
+                fir_name,
+                icao,
+                runway.designator,
+                runway.reciprocal,
+                runway.threshold.map(Point::x),
+                runway.threshold.map(Point::y),
+                runway.reciprocal_threshold.map(Point::x),
+                runway.reciprocal_threshold.map(Point::y),
+                runway.true_heading(),
+                runway.length(),
+                runway.elevation,
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+fn polygon_wkt(polygon: &Polygon) -> String {
+    fn ring(line_string: &LineString) -> String {
+        format!(
+            "({})",
+            line_string
+                .coords()
+                .map(|coord| format!("{} {}", coord.x, coord.y))
+                .join(", ")
+        )
+    }
+
+    format!(
+        "POLYGON ({})",
+        std::iter::once(polygon.exterior())
+            .chain(polygon.interiors())
+            .map(ring)
+            .join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use geo::polygon;
+    use rusqlite::Connection;
+
+    use crate::{
+        position::{PositionReference, StationType},
+        Config, OpenData, Position, Sector, Volume, FIR,
+    };
+
+    #[test]
+    fn test_export() {
+        let open_data = OpenData {
+            firs: BTreeMap::from([(
+                "EDMM".to_string(),
+                FIR {
+                    positions: BTreeMap::from([(
+                        "DMSD".to_string(),
+                        Position {
+                            frequency: 132_305_000,
+                            prefix: "EDDM".to_string(),
+                            station_type: StationType::Approach,
+                            radio_callsign: "München Director".to_string(),
+                            name: None,
+                            cpdlc_logon: None,
+                            airspace_groups: vec![],
+                            gcap_tier: None,
+                        },
+                    )]),
+                    volumes: BTreeMap::from([(
+                        "VOL1".to_string(),
+                        Volume::new(
+                            0,
+                            100,
+                            polygon![(x: 11.0, y: 48.0), (x: 12.0, y: 48.0), (x: 12.0, y: 49.0)],
+                        ),
+                    )]),
+                    sectors: BTreeMap::from([(
+                        "SEC1".to_string(),
+                        Sector {
+                            name: None,
+                            volumes: vec!["VOL1".to_string()],
+                            runway_filter: vec![],
+                            position_priority: vec![vec![
+                                PositionReference {
+                                    fir: None,
+                                    id: "DMSD".to_string(),
+                                },
+                                PositionReference {
+                                    fir: Some("EDGG".to_string()),
+                                    id: "GIXX".to_string(),
+                                },
+                            ]],
+                        },
+                    )]),
+                    ..Default::default()
+                },
+            )]),
+            config: Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_region = 2\n").unwrap(),
+        };
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        super::export_to_connection(&open_data, &mut conn).unwrap();
+
+        let app_freq: u32 = conn
+            .query_row(
+                "SELECT frequency FROM positions WHERE station_type = 'APP'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(app_freq, 132_305_000);
+
+        // APP positions on 8.33 kHz channels in VATEUD8 region 2
+        let app_833: Vec<String> = conn
+            .prepare(
+                "SELECT positions.id FROM positions JOIN firs ON positions.fir = firs.name \
+                 WHERE station_type = 'APP' AND frequency % 25000 != 0 AND vateud8_region = 2",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(app_833, ["DMSD"]);
+
+        let wkt: String = conn
+            .query_row("SELECT wkt FROM volumes WHERE id = 'VOL1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(wkt, "POLYGON ((11 48, 12 48, 12 49, 11 48))");
+
+        let dangling = conn
+            .prepare("PRAGMA foreign_key_check(sector_priorities)")
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(0))
+            .unwrap()
+            .count();
+        assert_eq!(dangling, 1);
+    }
+}
@@ -1,22 +1,469 @@
-use chrono::NaiveDate;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt, io,
+    path::PathBuf,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
 use itertools::Itertools;
+#[cfg(feature = "fetch")]
 use reqwest::blocking;
-use scraper::{Html, Selector};
-use serde::Serialize;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::OpenData;
+use crate::{OpenData, Position, StationType};
 
+#[cfg(feature = "fetch")]
 const VATEUD8_URL: &str = "https://fsmine.dhis.org/vateud8/";
 
-#[derive(Serialize)]
+/// A reference position list the data set is cross-checked against
+#[derive(Serialize, Deserialize)]
 pub struct Vateud8Data {
-    positions: Vec<Vateud8Position>,
+    /// When the list was retrieved from its source
+    #[serde(default)]
+    pub fetched_at: Option<DateTime<Utc>>,
+    positions: Vec<ReferencePosition>,
+    #[serde(skip)]
+    row_errors: Vec<RowError>,
+}
+
+impl From<Vec<ReferencePosition>> for Vateud8Data {
+    fn from(positions: Vec<ReferencePosition>) -> Self {
+        Self {
+            fetched_at: None,
+            positions,
+            row_errors: Vec::new(),
+        }
+    }
+}
+
+/// A position of an official list, e.g. the VATEUD8 list
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ReferencePosition {
+    pub region: u32,
+    /// Callsign as listed, e.g. `EDMM_CTR`
+    pub name: String,
+    /// Radio callsign
+    pub callsign: String,
+    /// In Hz
+    pub frequency: u32,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default)]
+    pub updated_at: Option<NaiveDate>,
+}
+
+impl ReferencePosition {
+    /// Whether `position` is this entry, i.e. has its frequency and a matching prefix
+    fn matches(&self, position: &Position) -> bool {
+        self.frequency == position.frequency && self.matches_prefix(position)
+    }
+
+    fn matches_prefix(&self, position: &Position) -> bool {
+        (!self.prefix.is_empty() && self.prefix.starts_with(&position.prefix))
+            || position.prefix.starts_with(self.name_prefix())
+    }
+
+    /// Start of the name, e.g. `EDMM` for `EDMM_CTR`
+    fn name_prefix(&self) -> &str {
+        self.name.split('_').next().unwrap_or_default()
+    }
+
+    fn station_type(&self) -> Option<StationType> {
+        self.name.rsplit('_').next()?.parse().ok()
+    }
+
+    /// The listed prefix, the start of the name if none is listed
+    fn effective_prefix(&self) -> &str {
+        if self.prefix.is_empty() {
+            self.name_prefix()
+        } else {
+            &self.prefix
+        }
+    }
+
+    /// Row as in the VATEUD8 table, e.g. `8 | EDMM_CTR | Muenchen Radar | 129.100 | EDMM`
+    #[must_use]
+    pub fn table_row(&self) -> String {
+        format!(
+            "{} | {} | {} | {} | {}",
+            self.region,
+            self.name,
+            self.callsign,
+            format_mhz(self.frequency),
+            self.prefix
+        )
+    }
+}
+
+fn format_mhz(frequency: u32) -> String {
+    format!(
+        "{}.{:03}",
+        frequency / 1_000_000,
+        frequency % 1_000_000 / 1_000
+    )
+}
+
+/// The entries the positions of all FIRs with `vateud8_region` correspond to, sorted by region
+/// and name. Positions in `vateud8_ignore` are left out.
+#[must_use]
+pub fn export(open_data: &OpenData) -> Vec<ReferencePosition> {
+    open_data
+        .firs
+        .iter()
+        .filter_map(|(fir_name, fir)| {
+            let fir_config = open_data.config.firs.get(fir_name)?;
+            Some((fir, fir_config, fir_config.vateud8_region?))
+        })
+        .flat_map(|(fir, fir_config, region)| {
+            fir.positions
+                .iter()
+                .filter(|(id, _)| !fir_config.vateud8_ignore.contains(id))
+                .map(move |(_, position)| ReferencePosition {
+                    region,
+                    name: position.callsign(),
+                    callsign: position.radio_callsign.clone(),
+                    frequency: position.frequency,
+                    prefix: position.prefix.clone(),
+                    updated_at: None,
+                })
+        })
+        .sorted_by(|a, b| (a.region, &a.name, a.frequency).cmp(&(b.region, &b.name, b.frequency)))
+        .collect()
+}
+
+/// Writes entries as CSV in the column order of the VATEUD8 table, frequencies in Hz as read
+/// back by [`ListFile`]
+pub fn to_table(positions: &[ReferencePosition]) -> Result<String, Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["region", "name", "callsign", "frequency", "prefix"])?;
+    for position in positions {
+        writer.write_record([
+            &position.region.to_string(),
+            &position.name,
+            &position.callsign,
+            &position.frequency.to_string(),
+            &position.prefix,
+        ])?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| Error::ParseCsv(e.into_error().into()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// A change to request from VATEUD8, see [`Vateud8Data::submission`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Submission {
+    Add {
+        proposed: ReferencePosition,
+    },
+    Change {
+        current: ReferencePosition,
+        proposed: ReferencePosition,
+    },
+    Remove {
+        current: ReferencePosition,
+    },
+}
+
+impl fmt::Display for Submission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Add { proposed } => write!(f, "add     {}", proposed.table_row()),
+            Self::Change { current, proposed } => write!(
+                f,
+                "change  {}\n     to {}",
+                current.table_row(),
+                proposed.table_row()
+            ),
+            Self::Remove { current } => write!(f, "remove  {}", current.table_row()),
+        }
+    }
+}
+
+/// A proposed change resolving a VATEUD8 mismatch, see [`Vateud8Data::fixes`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Fix {
+    /// Use the frequency of entry `reference`, which has a matching prefix
+    Frequency {
+        fir: String,
+        position: String,
+        frequency: u32,
+        reference: String,
+    },
+    /// Use the prefix of entry `reference`, which has the same frequency
+    Prefix {
+        fir: String,
+        position: String,
+        prefix: String,
+        reference: String,
+    },
+    /// No similar entry, add the position to `vateud8_ignore` of the FIR
+    IgnorePosition { fir: String, position: String },
+    /// No position for the entry, add it to `ignore_extra`
+    IgnoreExtra { name: String },
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Frequency {
+                fir,
+                position,
+                frequency,
+                reference,
+            } => write!(
+                f,
+                "{fir}-{position}: set frequency = {frequency} ({} MHz) as VATEUD8 {reference}",
+                format_mhz(*frequency)
+            ),
+            Self::Prefix {
+                fir,
+                position,
+                prefix,
+                reference,
+            } => write!(
+                f,
+                "{fir}-{position}: set prefix = \"{prefix}\" as VATEUD8 {reference}"
+            ),
+            Self::IgnorePosition { fir, position } => write!(
+                f,
+                "{fir}-{position}: no similar VATEUD8 entry, add it to firs.{fir}.vateud8_ignore"
+            ),
+            Self::IgnoreExtra { name } => write!(
+                f,
+                "{name}: no matching position, add it to vateud8.ignore_extra"
+            ),
+        }
+    }
+}
+
+/// A position matched with its VATEUD8 entry and the last update of both, see
+/// [`Vateud8Data::staleness`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Staleness {
+    pub fir: String,
+    pub position: String,
+    /// Name of the matched entry
+    pub reference: String,
+    pub region: u32,
+    pub vateud8_updated: Option<NaiveDate>,
+    /// Last change of the data, e.g. the commit date of `positions.toml`
+    pub data_updated: Option<NaiveDate>,
+}
+
+impl Staleness {
+    /// Whether VATEUD8 was updated after the data, e.g. for a frequency change not taken over
+    #[must_use]
+    pub fn is_outdated(&self) -> bool {
+        matches!(
+            (self.vateud8_updated, self.data_updated),
+            (Some(vateud8), Some(data)) if vateud8 > data
+        )
+    }
+}
+
+/// Applies the [`Fix::Frequency`] and [`Fix::Prefix`] fixes of FIR `fir` to the contents of its
+/// `positions.toml`, keeping all other lines including comments.
+#[must_use]
+pub fn patch_positions(contents: &str, fir: &str, fixes: &[Fix]) -> String {
+    let mut lines = contents
+        .split_inclusive('\n')
+        .map(str::to_string)
+        .collect_vec();
+    for fix in fixes {
+        let (position, key, value) = match fix {
+            Fix::Frequency {
+                fir: fix_fir,
+                position,
+                frequency,
+                ..
+            } if fix_fir == fir => (position, "frequency", frequency.to_string()),
+            Fix::Prefix {
+                fir: fix_fir,
+                position,
+                prefix,
+                ..
+            } if fix_fir == fir => (position, "prefix", format!("\"{prefix}\"")),
+            _ => continue,
+        };
+        let Some(header) = crate::diagnostic::find_definition(contents, position) else {
+            continue;
+        };
+        let Some(line) = lines
+            .iter_mut()
+            .skip(header + 1)
+            .take_while(|line| !line.trim_start().starts_with('['))
+            .find(|line| {
+                line.trim_start()
+                    .strip_prefix(key)
+                    .is_some_and(|rest| rest.trim_start().starts_with('='))
+            })
+        else {
+            continue;
+        };
+        let indent = &line[..line.len() - line.trim_start().len()];
+        let newline = if line.ends_with('\n') { "\n" } else { "" };
+        *line = format!("{indent}{key} = {value}{newline}");
+    }
+    lines.concat()
+}
+
+/// Where a reference list comes from, split into retrieving and parsing the raw list.
+pub trait PositionSource {
+    /// Raw contents of the list
+    fn fetch(&self) -> Result<String, Error>;
+
+    fn parse(&self, raw: &str) -> Result<Vateud8Data, Error>;
+
+    /// Fetches and parses the list, recording the time unless the list itself carries it
+    fn load(&self) -> Result<Vateud8Data, Error> {
+        let mut data = self.parse(&self.fetch()?)?;
+        data.fetched_at.get_or_insert_with(Utc::now);
+        Ok(data)
+    }
+}
+
+/// The VATEUD8 position list page
+#[cfg(feature = "fetch")]
+#[derive(Default)]
+pub struct Vateud8Page {
+    /// Alternative location of the page, the public VATEUD8 site if not given
+    pub url: Option<String>,
+}
+
+#[cfg(feature = "fetch")]
+impl PositionSource for Vateud8Page {
+    fn fetch(&self) -> Result<String, Error> {
+        Ok(blocking::get(self.url.as_deref().unwrap_or(VATEUD8_URL))?.text()?)
+    }
+
+    fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+        parse(raw)
+    }
+}
+
+/// A local list of [`ReferencePosition`]s, a CSV file with header row if the extension is
+/// `csv`, a JSON array otherwise.
+pub struct ListFile {
+    pub path: PathBuf,
+}
+
+impl PositionSource for ListFile {
+    fn fetch(&self) -> Result<String, Error> {
+        read_file(&self.path)
+    }
+
+    fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+        let positions: Vec<ReferencePosition> =
+            if self.path.extension().is_some_and(|ext| ext == "csv") {
+                csv::Reader::from_reader(raw.as_bytes())
+                    .deserialize()
+                    .collect::<Result<_, _>>()?
+            } else {
+                serde_json::from_str(raw)?
+            };
+        Ok(Vateud8Data::from(positions))
+    }
+}
+
+/// A list saved earlier as serialized [`Vateud8Data`], e.g. to run checks offline
+pub struct CachedSnapshot {
+    pub path: PathBuf,
+}
+
+impl PositionSource for CachedSnapshot {
+    fn fetch(&self) -> Result<String, Error> {
+        read_file(&self.path)
+    }
+
+    fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+        Ok(serde_json::from_str(raw)?)
+    }
+}
+
+/// Another source whose list is kept as snapshot at `path`. The snapshot is used while younger
+/// than `max_age` and as fallback with a warning when the source fails.
+#[cfg(feature = "fs")]
+pub struct CachedSource<S> {
+    pub source: S,
+    pub path: PathBuf,
+    pub max_age: chrono::TimeDelta,
+}
+
+#[cfg(feature = "fs")]
+impl<S: PositionSource> PositionSource for CachedSource<S> {
+    fn fetch(&self) -> Result<String, Error> {
+        self.source.fetch()
+    }
+
+    fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+        self.source.parse(raw)
+    }
+
+    fn load(&self) -> Result<Vateud8Data, Error> {
+        let fresh = |data: &Vateud8Data| {
+            data.fetched_at
+                .is_some_and(|fetched_at| Utc::now() - fetched_at < self.max_age)
+        };
+        let cached = match (CachedSnapshot {
+            path: self.path.clone(),
+        })
+        .load()
+        {
+            Ok(cached) if fresh(&cached) => {
+                debug!("using cached list from {:?}", cached.fetched_at);
+                return Ok(cached);
+            }
+            Ok(cached) => Some(cached),
+            Err(Error::Read(_, e)) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => {
+                warn!("ignoring cached list: {e}");
+                None
+            }
+        };
+        match self.source.load() {
+            Ok(data) => {
+                if let Err(e) = data.save(&self.path) {
+                    warn!("{e}");
+                }
+                Ok(data)
+            }
+            Err(e) => match cached {
+                Some(cached) => {
+                    let fetched_at = cached
+                        .fetched_at
+                        .map_or_else(|| "unknown time".to_string(), |time| time.to_string());
+                    warn!("{e}, using cached list from {fetched_at}");
+                    Ok(cached)
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+fn read_file(path: &std::path::Path) -> Result<String, Error> {
+    std::fs::read_to_string(path).map_err(|e| Error::Read(path.to_path_buf(), e))
+}
+
+#[cfg(not(feature = "fs"))]
+fn read_file(path: &std::path::Path) -> Result<String, Error> {
+    Err(Error::Read(
+        path.to_path_buf(),
+        io::Error::new(io::ErrorKind::Unsupported, "built without file access"),
+    ))
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[cfg(feature = "fetch")]
     #[error("could not fetch vateud8 data: {0}")]
     Fetch(#[from] reqwest::Error),
     #[error("VATEUD8 list, region mismatch: {0}-{1}, {2}!={3}")]
@@ -25,68 +472,407 @@ pub enum Error {
     NotFound(String, String),
     #[error("VATEUD8 list, extra position: {0}")]
     Superfluous(String),
+    #[error("VATEUD8 list, ignore_extra entry {0} matches no superfluous position")]
+    UnusedIgnoreExtra(String),
+    #[error("VATEUD8 list, no table with region, name, callsign, frequency and prefix columns")]
+    TableNotFound,
+    #[error("could not read reference list {path}: {1}", path = .0.display())]
+    Read(PathBuf, io::Error),
+    #[error("could not write reference list {path}: {1}", path = .0.display())]
+    Write(PathBuf, io::Error),
+    #[error("invalid reference list: {0}")]
+    ParseCsv(#[from] csv::Error),
+    #[error("invalid reference list: {0}")]
+    ParseJson(#[from] serde_json::Error),
 }
 
-fn fetch_html(url: Option<&str>) -> Result<String, Error> {
-    Ok(blocking::get(url.unwrap_or(VATEUD8_URL))?.text()?)
+/// A row of the VATEUD8 position table that could not be parsed
+#[derive(Debug, Error)]
+#[error("VATEUD8 list, skipping row {row} ({content}): {kind}")]
+pub struct RowError {
+    /// 1-based, counting the header row
+    pub row: usize,
+    /// Cell texts separated by ` | `
+    pub content: String,
+    pub kind: RowErrorKind,
 }
 
-#[derive(Serialize, Clone, Debug, PartialEq, Eq, Hash)]
-struct Vateud8Position {
-    region: u32,
-    name: String,
-    callsign: String,
-    frequency: u32,
-    prefix: String,
-    updated_at: Option<NaiveDate>,
+#[derive(Debug, Error)]
+pub enum RowErrorKind {
+    #[error("missing {0} cell")]
+    MissingCell(&'static str),
+    #[error("invalid region {0:?}")]
+    InvalidRegion(String),
+    #[error("invalid frequency {0:?}")]
+    InvalidFrequency(String),
 }
 
+impl Error {
+    /// FIRs an error concerns, empty for errors not tied to a FIR
+    #[must_use]
+    pub fn firs(&self) -> Vec<&str> {
+        match self {
+            Self::RegionMismatch(fir, ..) | Self::NotFound(fir, _) => vec![fir],
+            #[cfg(feature = "fetch")]
+            Self::Fetch(_) => vec![],
+            Self::Superfluous(_)
+            | Self::UnusedIgnoreExtra(_)
+            | Self::TableNotFound
+            | Self::Read(..)
+            | Self::Write(..)
+            | Self::ParseCsv(_)
+            | Self::ParseJson(_) => vec![],
+        }
+    }
+}
+
+/// Downloads and parses the position list, from the public VATEUD8 site unless `url` is given.
+#[cfg(feature = "fetch")]
 pub fn get(url: Option<&str>) -> Result<Vateud8Data, Error> {
-    let body = fetch_html(url)?;
-    let doc = Html::parse_document(&body);
-
-    let positions = doc
-        .select(&Selector::parse("table").unwrap())
-        .nth(1)
-        .unwrap()
-        .select(&Selector::parse("tr").unwrap())
-        .skip(1)
-        .map(|tr| {
-            let td = Selector::parse("td").unwrap();
-            let mut cells = tr.select(&td);
-            Vateud8Position {
-                region: cells.next().unwrap().text().join("").parse().unwrap(),
-                name: cells.next().unwrap().text().join(""),
-                callsign: cells.next().unwrap().text().join(""),
-                #[allow(
-                    clippy::cast_sign_loss,
-                    clippy::cast_possible_truncation,
-                    reason = "can only be positive and in range"
-                )]
-                frequency: (cells
-                    .next()
-                    .unwrap()
-                    .text()
-                    .join("")
-                    .parse::<f64>()
-                    .unwrap()
-                    * 1_000.0)
-                    .round() as u32
-                    * 1000,
-                prefix: cells.next().unwrap().text().join(""),
-                updated_at: {
-                    let date_str = cells.next().unwrap().text().join("");
-                    NaiveDate::parse_and_remainder(&date_str, "%Y-%m-%d")
-                        .ok()
-                        .map(|(date, _)| date)
-                },
-            }
+    Vateud8Page {
+        url: url.map(ToString::to_string),
+    }
+    .load()
+}
+
+/// Columns of the position table and the header names they are recognized by, after
+/// lowercasing and removing everything but letters.
+const COLUMNS: [(&str, &[&str]); 6] = [
+    ("region", &["region"]),
+    ("name", &["name"]),
+    ("callsign", &["callsign"]),
+    ("frequency", &["frequency", "freq"]),
+    ("prefix", &["prefix"]),
+    ("updated", &["updated", "updatedat", "lastupdated", "date"]),
+];
+
+/// Parses the position list page, e.g. when it was downloaded by other means.
+///
+/// The position table is the first one whose header row names all columns except the optional
+/// update date, in any order. Malformed rows are skipped with a warning and kept in
+/// [`Vateud8Data::row_errors`].
+pub fn parse(html: &str) -> Result<Vateud8Data, Error> {
+    let doc = Html::parse_document(html);
+    let table = Selector::parse("table").expect("valid selector");
+    let tr = Selector::parse("tr").expect("valid selector");
+    let cell = Selector::parse("th, td").expect("valid selector");
+    let text = |element: ElementRef| element.text().join("").trim().to_string();
+
+    let (rows, columns) = doc
+        .select(&table)
+        .find_map(|table| {
+            let mut rows = table.select(&tr);
+            let headers = rows
+                .next()?
+                .select(&cell)
+                .map(|th| {
+                    text(th)
+                        .to_lowercase()
+                        .replace(|c: char| !c.is_alphabetic(), "")
+                })
+                .collect_vec();
+            let columns = COLUMNS.map(|(_, names)| {
+                headers
+                    .iter()
+                    .position(|header| names.contains(&header.as_str()))
+            });
+            columns[..5]
+                .iter()
+                .all(Option::is_some)
+                .then_some((rows, columns))
         })
-        .collect();
-    Ok(Vateud8Data { positions })
+        .ok_or(Error::TableNotFound)?;
+
+    let mut positions = Vec::new();
+    let mut row_errors = Vec::new();
+    for (idx, row) in rows.enumerate() {
+        let cells = row.select(&cell).map(text).collect_vec();
+        if cells.iter().all(String::is_empty) {
+            continue;
+        }
+        match parse_row(&cells, &columns) {
+            Ok(position) => positions.push(position),
+            Err(kind) => {
+                let e = RowError {
+                    row: idx + 2,
+                    content: cells.join(" | "),
+                    kind,
+                };
+                warn!("{e}");
+                row_errors.push(e);
+            }
+        }
+    }
+    Ok(Vateud8Data {
+        fetched_at: None,
+        positions,
+        row_errors,
+    })
+}
+
+fn parse_row(
+    cells: &[String],
+    columns: &[Option<usize>; COLUMNS.len()],
+) -> Result<ReferencePosition, RowErrorKind> {
+    let [region, name, callsign, frequency, prefix, updated] =
+        std::array::from_fn(|idx| columns[idx].and_then(|column| cells.get(column)));
+    let required = |idx: usize, value: Option<&String>| {
+        value
+            .cloned()
+            .ok_or(RowErrorKind::MissingCell(COLUMNS[idx].0))
+    };
+
+    let region = required(0, region)?;
+    let frequency = required(3, frequency)?;
+    let mhz = frequency
+        .parse::<f64>()
+        .ok()
+        .filter(|mhz| (0.0..1_000.0).contains(mhz))
+        .ok_or(RowErrorKind::InvalidFrequency(frequency))?;
+    Ok(ReferencePosition {
+        region: region
+            .parse()
+            .map_err(|_| RowErrorKind::InvalidRegion(region.clone()))?,
+        name: required(1, name)?,
+        callsign: required(2, callsign)?,
+        #[allow(
+            clippy::cast_sign_loss,
+            clippy::cast_possible_truncation,
+            reason = "checked to be positive and in range"
+        )]
+        frequency: (mhz * 1_000.0).round() as u32 * 1000,
+        prefix: required(4, prefix)?,
+        updated_at: updated.and_then(|date| {
+            NaiveDate::parse_and_remainder(date, "%Y-%m-%d")
+                .ok()
+                .map(|(date, _)| date)
+        }),
+    })
 }
 
 impl Vateud8Data {
+    #[must_use]
+    pub fn positions(&self) -> &[ReferencePosition] {
+        &self.positions
+    }
+
+    /// Writes the list as JSON snapshot, readable with [`CachedSnapshot`]
+    #[cfg(feature = "fs")]
+    pub fn save(&self, path: &std::path::Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self)? + "\n";
+        std::fs::write(path, json).map_err(|e| Error::Write(path.to_path_buf(), e))
+    }
+
+    /// Rows of the position table that were skipped because they could not be parsed
+    #[must_use]
+    pub fn row_errors(&self) -> &[RowError] {
+        &self.row_errors
+    }
+
+    /// Matched positions with the `updated_at` date of their entry and the date `data_updated`
+    /// returns for their FIR. Positions in `vateud8_ignore` are left out.
+    pub fn staleness(
+        &self,
+        open_data: &OpenData,
+        data_updated: impl Fn(&str) -> Option<NaiveDate>,
+    ) -> Vec<Staleness> {
+        let mut staleness = Vec::new();
+        for (fir_name, fir) in &open_data.firs {
+            let ignore = open_data
+                .config
+                .firs
+                .get(fir_name)
+                .map(|fir_config| fir_config.vateud8_ignore.as_slice())
+                .unwrap_or_default();
+            let data_updated = data_updated(fir_name);
+            for (position_name, position) in &fir.positions {
+                if ignore.contains(position_name) {
+                    continue;
+                }
+                if let Some(reference) = self.positions.iter().find(|r| r.matches(position)) {
+                    staleness.push(Staleness {
+                        fir: fir_name.clone(),
+                        position: position_name.clone(),
+                        reference: reference.name.clone(),
+                        region: reference.region,
+                        vateud8_updated: reference.updated_at,
+                        data_updated,
+                    });
+                }
+            }
+        }
+        staleness
+    }
+
+    /// Per region the at most `limit` entries verified longest ago, those without `updated_at`
+    /// first
+    #[must_use]
+    pub fn oldest_unverified(&self, limit: usize) -> BTreeMap<u32, Vec<&ReferencePosition>> {
+        self.positions
+            .iter()
+            .sorted_by_key(|reference| reference.updated_at)
+            .into_group_map_by(|reference| reference.region)
+            .into_iter()
+            .map(|(region, mut references)| {
+                references.truncate(limit);
+                (region, references)
+            })
+            .collect()
+    }
+
+    /// Changes turning the list into the [`export`] of `open_data`. Entries are paired by name
+    /// and frequency, then by name alone. Only entries of regions configured for a FIR are
+    /// proposed for removal, except ATIS and `ignore_extra` entries.
+    #[must_use]
+    pub fn submission(&self, open_data: &OpenData) -> Vec<Submission> {
+        let regions = open_data
+            .config
+            .firs
+            .values()
+            .filter_map(|fir_config| fir_config.vateud8_region)
+            .collect::<HashSet<_>>();
+        let mut current = self
+            .positions
+            .iter()
+            .filter(|reference| {
+                regions.contains(&reference.region)
+                    && !reference.name.ends_with("_ATIS")
+                    && !open_data
+                        .config
+                        .vateud8
+                        .ignore_extra
+                        .contains(&reference.name)
+            })
+            .collect_vec();
+
+        let mut submission = Vec::new();
+        let mut unpaired = Vec::new();
+        for proposed in export(open_data) {
+            match current
+                .iter()
+                .position(|c| c.name == proposed.name && c.frequency == proposed.frequency)
+            {
+                Some(index) => {
+                    let current = current.remove(index);
+                    if current.region != proposed.region
+                        || current.callsign != proposed.callsign
+                        || current.effective_prefix() != proposed.prefix
+                    {
+                        submission.push(Submission::Change {
+                            current: current.clone(),
+                            proposed,
+                        });
+                    }
+                }
+                None => unpaired.push(proposed),
+            }
+        }
+        for proposed in unpaired {
+            match current.iter().position(|c| c.name == proposed.name) {
+                Some(index) => submission.push(Submission::Change {
+                    current: current.remove(index).clone(),
+                    proposed,
+                }),
+                None => submission.push(Submission::Add { proposed }),
+            }
+        }
+        submission.extend(current.into_iter().map(|current| Submission::Remove {
+            current: current.clone(),
+        }));
+        submission
+    }
+
+    /// Fix proposals for the [`Error::NotFound`] and [`Error::Superfluous`] results of
+    /// [`Vateud8Data::check`] from the closest entry with the same station type, preferring the
+    /// FIR's region: one with matching prefix gives the frequency, otherwise one with the same
+    /// frequency gives the prefix. Without such entry, or for extra entries not used by another
+    /// fix, ignoring them is proposed.
+    #[must_use]
+    pub fn fixes(&self, open_data: &OpenData) -> Vec<Fix> {
+        let errors = self.check(open_data).err().unwrap_or_default();
+        let mut fixes = errors
+            .iter()
+            .filter_map(|e| match e {
+                Error::NotFound(fir, position) => Some(self.closest(open_data, fir, position)),
+                _ => None,
+            })
+            .collect_vec();
+        let referenced = fixes
+            .iter()
+            .filter_map(|fix| match fix {
+                Fix::Frequency { reference, .. } | Fix::Prefix { reference, .. } => {
+                    Some(reference.clone())
+                }
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+        fixes.extend(errors.iter().filter_map(|e| match e {
+            Error::Superfluous(name) if !referenced.contains(name) => {
+                Some(Fix::IgnoreExtra { name: name.clone() })
+            }
+            _ => None,
+        }));
+        fixes
+    }
+
+    fn closest(&self, open_data: &OpenData, fir: &str, position_id: &str) -> Fix {
+        let ignore = Fix::IgnorePosition {
+            fir: fir.to_string(),
+            position: position_id.to_string(),
+        };
+        let Some(position) = open_data
+            .firs
+            .get(fir)
+            .and_then(|f| f.positions.get(position_id))
+        else {
+            return ignore;
+        };
+        let region = open_data
+            .config
+            .firs
+            .get(fir)
+            .and_then(|fir_config| fir_config.vateud8_region);
+        let candidates = self
+            .positions
+            .iter()
+            .filter(|reference| reference.station_type().as_ref() == Some(&position.station_type));
+
+        if let Some(reference) = candidates
+            .clone()
+            .filter(|reference| reference.matches_prefix(position))
+            .min_by_key(|reference| {
+                (
+                    Some(reference.region) != region,
+                    reference.prefix.len().abs_diff(position.prefix.len()),
+                )
+            })
+        {
+            return Fix::Frequency {
+                fir: fir.to_string(),
+                position: position_id.to_string(),
+                frequency: reference.frequency,
+                reference: reference.name.clone(),
+            };
+        }
+        if let Some(reference) = candidates
+            .filter(|reference| reference.frequency == position.frequency)
+            .min_by_key(|reference| Some(reference.region) != region)
+        {
+            let prefix = if reference.prefix.is_empty() {
+                reference.name_prefix()
+            } else {
+                &reference.prefix
+            };
+            return Fix::Prefix {
+                fir: fir.to_string(),
+                position: position_id.to_string(),
+                prefix: prefix.to_string(),
+                reference: reference.name.clone(),
+            };
+        }
+        ignore
+    }
+
     pub fn check(&self, open_data: &OpenData) -> Result<(), Vec<Error>> {
         let errors = open_data
             .firs
@@ -105,12 +891,7 @@ impl Vateud8Data {
                     .filter(|(pos_name, _)| !fir_config.vateud8_ignore.contains(pos_name))
                     .filter_map(move |(position_name, position)| {
                         if let Some(v8_pos) = self.positions.iter().find(|vateud8_pos| {
-                            let matches = vateud8_pos.frequency == position.frequency
-                                && ((!vateud8_pos.prefix.is_empty()
-                                    && vateud8_pos.prefix.starts_with(&position.prefix))
-                                    || position
-                                        .prefix
-                                        .starts_with(vateud8_pos.name.split('_').next().unwrap()));
+                            let matches = vateud8_pos.matches(position);
                             debug!("{vateud8_pos:?}-{position:?}: {matches}");
                             matches
                         }) {
@@ -133,36 +914,24 @@ impl Vateud8Data {
                 self.positions
                     .iter()
                     .filter(|v8_pos| {
-                        !v8_pos.name.ends_with("_ATIS")
-                            && !open_data
-                                .config
-                                .vateud8
-                                .ignore_regions
-                                .contains(&v8_pos.region)
-                            && !open_data.config.vateud8.ignore_extra.contains(&v8_pos.name)
+                        !open_data.config.vateud8.ignore_extra.contains(&v8_pos.name)
+                            && superfluous(open_data, v8_pos)
                     })
-                    .filter_map(|vateud8_pos| {
-                        if open_data
-                            .config
-                            .firs
+                    .map(|v8_pos| Error::Superfluous(v8_pos.name.clone())),
+            )
+            .chain(
+                open_data
+                    .config
+                    .vateud8
+                    .ignore_extra
+                    .iter()
+                    .filter(|name| {
+                        !self
+                            .positions
                             .iter()
-                            .filter(|(_, c)| c.vateud8_region == Some(vateud8_pos.region))
-                            .filter_map(|(fir_name, _)| open_data.firs.get(fir_name))
-                            .flat_map(|fir| &fir.positions)
-                            .any(|(_, position)| {
-                                vateud8_pos.frequency == position.frequency
-                                    && ((!vateud8_pos.prefix.is_empty()
-                                        && vateud8_pos.prefix.starts_with(&position.prefix))
-                                        || position.prefix.starts_with(
-                                            vateud8_pos.name.split('_').next().unwrap(),
-                                        ))
-                            })
-                        {
-                            None
-                        } else {
-                            Some(Error::Superfluous(vateud8_pos.name.clone()))
-                        }
-                    }),
+                            .any(|p| &&p.name == name && superfluous(open_data, p))
+                    })
+                    .map(|name| Error::UnusedIgnoreExtra(name.clone())),
             )
             .collect::<Vec<_>>();
 
@@ -173,3 +942,370 @@ impl Vateud8Data {
         }
     }
 }
+
+/// Whether `v8_pos` has no counterpart in the data and is not exempt as ATIS or by region
+fn superfluous(open_data: &OpenData, v8_pos: &ReferencePosition) -> bool {
+    !v8_pos.name.ends_with("_ATIS")
+        && !open_data
+            .config
+            .vateud8
+            .ignore_regions
+            .contains(&v8_pos.region)
+        && !open_data
+            .config
+            .firs
+            .iter()
+            .filter(|(_, c)| c.vateud8_region == Some(v8_pos.region))
+            .filter_map(|(fir_name, _)| open_data.firs.get(fir_name))
+            .flat_map(|fir| &fir.positions)
+            .any(|(_, position)| v8_pos.matches(position))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{Error, RowErrorKind};
+
+    #[test]
+    fn test_parse_fixtures() {
+        let data = super::parse(include_str!("../tests/fixtures/vateud8.html")).unwrap();
+
+        let names = data
+            .positions
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["EDMM_CTR", "EDDM_ATIS", "LOVV_CTR"]);
+        let edmm = &data.positions[0];
+        assert_eq!(edmm.region, 8);
+        assert_eq!(edmm.callsign, "Muenchen Radar");
+        assert_eq!(edmm.frequency, 129_100_000);
+        assert_eq!(edmm.prefix, "EDMM");
+        assert_eq!(edmm.updated_at, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(data.positions[1].updated_at, None);
+
+        let errors = data.row_errors();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].row, 5);
+        assert!(matches!(&errors[0].kind, RowErrorKind::InvalidFrequency(f) if f == "n/a"));
+        assert_eq!(
+            errors[0].content,
+            "8 | EDGG_CTR | Langen Radar | n/a | EDGG |  | 2023-11-20"
+        );
+        assert!(matches!(&errors[1].kind, RowErrorKind::InvalidRegion(r) if r == "EU"));
+        assert!(matches!(
+            errors[2].kind,
+            RowErrorKind::MissingCell("frequency")
+        ));
+        // reported by the CLI as warnings
+        let diagnostic = crate::diagnostic::Diagnostic::from(&errors[0]);
+        assert_eq!(diagnostic.severity, crate::diagnostic::Severity::Warning);
+        assert!(diagnostic.to_string().starts_with(
+            "warning[vateud8-skipped-row]: VATEUD8 list, skipping row 5 (8 | EDGG_CTR"
+        ));
+
+        assert!(matches!(
+            super::parse(include_str!(
+                "../tests/fixtures/vateud8-changed-layout.html"
+            )),
+            Err(Error::TableNotFound)
+        ));
+        assert!(matches!(super::parse(""), Err(Error::TableNotFound)));
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_sources() {
+        use super::{PositionSource, Vateud8Data};
+        use crate::{Config, OpenData};
+
+        /// The fixture page as if it was downloaded
+        struct Fixture;
+
+        impl PositionSource for Fixture {
+            fn fetch(&self) -> Result<String, Error> {
+                Ok(include_str!("../tests/fixtures/vateud8.html").to_string())
+            }
+
+            fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+                super::parse(raw)
+            }
+        }
+
+        let mut open_data = OpenData {
+            config: Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_region = 8\n").unwrap(),
+            ..Default::default()
+        };
+        open_data
+            .firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+
+        let dir = std::env::temp_dir().join(format!("vod-vateud8-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("list.csv");
+        std::fs::write(
+            &csv,
+            "region,name,callsign,frequency,prefix,updated_at\n\
+             8,EDMM_CTR,Muenchen Radar,129100000,EDMM,2024-03-01\n\
+             1,LOVV_CTR,Wien Radar,134675000,LOVV,\n",
+        )
+        .unwrap();
+        let from_csv = super::ListFile { path: csv }.load().unwrap();
+        let from_page = Fixture.load().unwrap();
+        assert_eq!(from_csv.positions[0], from_page.positions[0]);
+        assert_eq!(from_csv.positions[1], from_page.positions[2]);
+
+        let json = dir.join("list.json");
+        std::fs::write(&json, serde_json::to_string(&from_csv.positions).unwrap()).unwrap();
+        let from_json = super::ListFile { path: json }.load().unwrap();
+        let snapshot = dir.join("snapshot.json");
+        std::fs::write(&snapshot, serde_json::to_string(&from_page).unwrap()).unwrap();
+        let from_snapshot = super::CachedSnapshot { path: snapshot }.load().unwrap();
+        let missing = super::ListFile {
+            path: dir.join("missing.csv"),
+        }
+        .load();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(from_json.positions, from_csv.positions);
+        assert_eq!(from_snapshot.positions, from_page.positions);
+        assert!(matches!(missing, Err(Error::Read(..))));
+
+        // the same cross-checks run against every source
+        for data in [from_csv, from_json, from_page, from_snapshot] {
+            let errors = data.check(&open_data).unwrap_err();
+            assert!(errors.iter().all(|e| matches!(e, Error::Superfluous(_))));
+        }
+    }
+
+    #[test]
+    fn test_fixes() {
+        use super::{Fix, ReferencePosition, Vateud8Data};
+        use crate::{Config, OpenData};
+
+        let mut open_data = OpenData {
+            config: Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_region = 8\n").unwrap(),
+            ..Default::default()
+        };
+        let fir = crate::tests::euroscope_fir();
+        let fir_files = fir.to_files().unwrap();
+        open_data.firs.insert("EDMM".to_string(), fir);
+        let reference = |region, name: &str, frequency, updated_at| ReferencePosition {
+            region,
+            name: name.to_string(),
+            callsign: String::new(),
+            frequency,
+            prefix: String::new(),
+            updated_at,
+        };
+        let data = Vateud8Data::from(vec![
+            reference(8, "EDMM_CTR", 128_000_000, None),
+            reference(1, "LOVV_CTR", 134_675_000, None),
+        ]);
+
+        let fixes = data.fixes(&open_data);
+        assert_eq!(
+            fixes,
+            [
+                Fix::Frequency {
+                    fir: "EDMM".to_string(),
+                    position: "MMR".to_string(),
+                    frequency: 128_000_000,
+                    reference: "EDMM_CTR".to_string(),
+                },
+                Fix::IgnoreExtra {
+                    name: "LOVV_CTR".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            fixes[0].to_string(),
+            "EDMM-MMR: set frequency = 128000000 (128.000 MHz) as VATEUD8 EDMM_CTR"
+        );
+
+        let positions = &fir_files
+            .iter()
+            .find(|(name, _)| *name == "positions.toml")
+            .unwrap()
+            .1;
+        let patched = super::patch_positions(positions, "EDMM", &fixes);
+        let edmm = open_data.firs.get_mut("EDMM").unwrap();
+        edmm.set_file("positions.toml", &patched).unwrap();
+        assert_eq!(edmm.positions["MMR"].frequency, 128_000_000);
+        assert_eq!(data.fixes(&open_data), fixes[1..]);
+
+        // entries missing from the list and entries matching a position both suppress nothing
+        open_data.config.vateud8.ignore_extra = ["EDMM_CTR", "LOVV_CTR", "EDXX_CTR"]
+            .map(ToString::to_string)
+            .to_vec();
+        let unused = data
+            .check(&open_data)
+            .unwrap_err()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unused,
+            [
+                "VATEUD8 list, ignore_extra entry EDMM_CTR matches no superfluous position",
+                "VATEUD8 list, ignore_extra entry EDXX_CTR matches no superfluous position",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_staleness() {
+        use super::{ReferencePosition, Vateud8Data};
+        use crate::{Config, OpenData};
+
+        let mut open_data = OpenData {
+            config: Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_region = 8\n").unwrap(),
+            ..Default::default()
+        };
+        open_data
+            .firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        let reference = |name: &str, frequency, updated_at| ReferencePosition {
+            region: 8,
+            name: name.to_string(),
+            callsign: String::new(),
+            frequency,
+            prefix: String::new(),
+            updated_at,
+        };
+        let january = NaiveDate::from_ymd_opt(2024, 1, 1);
+        let march = NaiveDate::from_ymd_opt(2024, 3, 1);
+        let data = Vateud8Data::from(vec![
+            reference("EDMM_CTR", 129_100_000, march),
+            reference("EDDM_TWR", 118_700_000, None),
+            reference("EDDN_TWR", 118_300_000, january),
+        ]);
+
+        let staleness = data.staleness(&open_data, |_| january);
+        assert_eq!(staleness.len(), 1);
+        assert_eq!(staleness[0].fir, "EDMM");
+        assert_eq!(staleness[0].position, "MMR");
+        assert_eq!(staleness[0].reference, "EDMM_CTR");
+        assert!(staleness[0].is_outdated());
+        assert!(!data.staleness(&open_data, |_| march)[0].is_outdated());
+        // without a date on either side nothing is known to be outdated
+        assert!(!data.staleness(&open_data, |_| None)[0].is_outdated());
+
+        let ignore = Config::from_toml_str(
+            "[vateud8]\n[firs.EDMM]\nvateud8_region = 8\nvateud8_ignore = [\"MMR\"]\n",
+        );
+        open_data.config = ignore.unwrap();
+        assert!(data.staleness(&open_data, |_| january).is_empty());
+
+        let oldest = data.oldest_unverified(2);
+        let names = oldest[&8]
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["EDDM_TWR", "EDDN_TWR"]);
+        assert_eq!(data.oldest_unverified(5)[&8].len(), 3);
+    }
+
+    #[test]
+    fn test_submission() {
+        use super::{ListFile, PositionSource, ReferencePosition, Submission, Vateud8Data};
+        use crate::{Config, OpenData};
+
+        let mut open_data = OpenData {
+            config: Config::from_toml_str("[vateud8]\n[firs.EDMM]\nvateud8_region = 8\n").unwrap(),
+            ..Default::default()
+        };
+        open_data
+            .firs
+            .insert("EDMM".to_string(), crate::tests::euroscope_fir());
+        let exported = super::export(&open_data);
+        let table = super::to_table(&exported).unwrap();
+        assert_eq!(
+            table,
+            "region,name,callsign,frequency,prefix\n8,EDMM_CTR,München Radar,129100000,EDMM\n"
+        );
+        let list_file = ListFile {
+            path: "export.csv".into(),
+        };
+        assert_eq!(list_file.parse(&table).unwrap().positions, exported);
+
+        let reference = |name: &str, callsign: &str| ReferencePosition {
+            region: 8,
+            name: name.to_string(),
+            callsign: callsign.to_string(),
+            frequency: 129_100_000,
+            prefix: String::new(),
+            updated_at: None,
+        };
+        let current = Vateud8Data::from(vec![
+            reference("EDMM_CTR", "Muenchen Radar"),
+            reference("EDDM_TWR", "Muenchen Tower"),
+            reference("EDDM_ATIS", "Muenchen ATIS"),
+        ]);
+        assert_eq!(
+            current.submission(&open_data),
+            [
+                Submission::Change {
+                    current: reference("EDMM_CTR", "Muenchen Radar"),
+                    proposed: exported[0].clone(),
+                },
+                Submission::Remove {
+                    current: reference("EDDM_TWR", "Muenchen Tower"),
+                },
+            ]
+        );
+        assert_eq!(
+            Vateud8Data::from(exported.clone()).submission(&open_data),
+            []
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn test_cache() {
+        use chrono::{TimeDelta, Utc};
+
+        use super::{CachedSnapshot, CachedSource, PositionSource, Vateud8Data};
+
+        /// A source that is down, or up with a single position
+        struct Source(bool);
+
+        impl PositionSource for Source {
+            fn fetch(&self) -> Result<String, Error> {
+                if self.0 {
+                    Ok(include_str!("../tests/fixtures/vateud8.html").to_string())
+                } else {
+                    Err(Error::TableNotFound)
+                }
+            }
+
+            fn parse(&self, raw: &str) -> Result<Vateud8Data, Error> {
+                super::parse(raw)
+            }
+        }
+
+        let path = std::env::temp_dir().join(format!("vod-cache-{}.json", std::process::id()));
+        let cached = |up| CachedSource {
+            source: Source(up),
+            path: path.clone(),
+            max_age: TimeDelta::days(1),
+        };
+
+        assert!(matches!(cached(false).load(), Err(Error::TableNotFound)));
+
+        let mut stale = Vateud8Data::from(vec![]);
+        stale.fetched_at = Some(Utc::now() - TimeDelta::days(2));
+        stale.save(&path).unwrap();
+        // falls back to the stale list when the source is down
+        assert!(cached(false).load().unwrap().positions.is_empty());
+        // replaces it when the source is up
+        assert_eq!(cached(true).load().unwrap().positions.len(), 3);
+        let saved = CachedSnapshot { path: path.clone() }.load().unwrap();
+        assert_eq!(saved.positions.len(), 3);
+        assert!(saved.fetched_at.unwrap() > Utc::now() - TimeDelta::minutes(1));
+        // and uses the fresh list without fetching
+        assert_eq!(cached(false).load().unwrap().positions.len(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
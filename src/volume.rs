@@ -1,8 +1,10 @@
+use std::collections::BTreeMap;
 use std::io;
-use std::{collections::HashMap, fs::read_to_string, path::Path};
+#[cfg(feature = "fs")]
+use std::{fs::read_to_string, path::Path};
 
 use geo::Polygon;
-use geojson::{feature::Id, GeoJson};
+use geojson::{feature::Id, Feature, FeatureCollection, GeoJson, Geometry};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
@@ -39,7 +41,13 @@ pub enum ReadError {
     #[error("no FeatureCollection in {0}")]
     NoFeatureCollection(String),
     #[error("failed to deserialize geojson file: {0}")]
-    GeoJsonDeserialize(#[from] geojson::Error),
+    GeoJsonDeserialize(Box<geojson::Error>),
+}
+
+impl From<geojson::Error> for ReadError {
+    fn from(e: geojson::Error) -> Self {
+        Self::GeoJsonDeserialize(Box::new(e))
+    }
 }
 
 #[derive(Debug, Error)]
@@ -51,74 +59,111 @@ pub enum ConstraintError {
 }
 
 impl Volume {
-    pub fn from_geojson(path: &Path) -> Result<HashMap<String, Self>, ReadError> {
-        let geojson_str = read_to_string(path)?;
+    #[must_use]
+    pub fn new(lower_level: u64, upper_level: u64, lateral_bounds: Polygon) -> Self {
+        Self {
+            lower_level,
+            upper_level,
+            lateral_bounds,
+        }
+    }
+
+    #[must_use]
+    pub fn lower_level(&self) -> u64 {
+        self.lower_level
+    }
+
+    #[must_use]
+    pub fn upper_level(&self) -> u64 {
+        self.upper_level
+    }
+
+    #[must_use]
+    pub fn lateral_bounds(&self) -> &Polygon {
+        &self.lateral_bounds
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn from_geojson(path: &Path) -> Result<BTreeMap<String, Self>, ReadError> {
+        Self::from_geojson_str(&read_to_string(path)?, &path.display().to_string())
+    }
+
+    /// Parses the contents of a `volumes.geojson` file, `source` names it in errors.
+    pub fn from_geojson_str(
+        geojson_str: &str,
+        source: &str,
+    ) -> Result<BTreeMap<String, Self>, ReadError> {
         let geojson = geojson_str.parse::<GeoJson>()?;
         if let GeoJson::FeatureCollection(feature_collection) = geojson {
-            feature_collection
-                .features
-                .iter()
-                .map(|feature| match feature.id {
+            crate::map_ordered(
+                feature_collection.features.iter().collect(),
+                |feature| match feature.id {
                     Some(Id::String(ref id)) => Ok((
                         id.clone(),
                         Self {
                             lateral_bounds: feature
                                 .geometry
                                 .as_ref()
-                                .ok_or(ReadError::MissingGeometry(
-                                    id.clone(),
-                                    path.display().to_string(),
-                                ))?
+                                .ok_or(ReadError::MissingGeometry(id.clone(), source.to_string()))?
                                 .value
                                 .clone()
                                 .try_into()?,
                             lower_level: feature
                                 .property("lower_level")
                                 .ok_or_else(|| {
-                                    ReadError::MissingLowerLevel(
-                                        id.clone(),
-                                        path.display().to_string(),
-                                    )
+                                    ReadError::MissingLowerLevel(id.clone(), source.to_string())
                                 })?
                                 .as_u64()
                                 .ok_or_else(|| {
-                                    ReadError::InvalidLowerLevel(
-                                        id.clone(),
-                                        path.display().to_string(),
-                                    )
+                                    ReadError::InvalidLowerLevel(id.clone(), source.to_string())
                                 })?,
                             upper_level: feature
                                 .property("upper_level")
                                 .ok_or_else(|| {
-                                    ReadError::MissingUpperLevel(
-                                        id.clone(),
-                                        path.display().to_string(),
-                                    )
+                                    ReadError::MissingUpperLevel(id.clone(), source.to_string())
                                 })?
                                 .as_u64()
                                 .ok_or_else(|| {
-                                    ReadError::InvalidUpperLevel(
-                                        id.clone(),
-                                        path.display().to_string(),
-                                    )
+                                    ReadError::InvalidUpperLevel(id.clone(), source.to_string())
                                 })?,
                         },
                     )),
-                    Some(Id::Number(ref id)) => Err(ReadError::InvalidId(
-                        id.to_string(),
-                        path.display().to_string(),
-                    )),
-                    None => Err(ReadError::MissingId(path.display().to_string())),
-                })
-                .fold_ok(HashMap::new(), |mut acc, (id, volume)| {
-                    acc.insert(id, volume);
-                    acc
-                })
+                    Some(Id::Number(ref id)) => {
+                        Err(ReadError::InvalidId(id.to_string(), source.to_string()))
+                    }
+                    None => Err(ReadError::MissingId(source.to_string())),
+                },
+            )
+            .into_iter()
+            .fold_ok(BTreeMap::new(), |mut acc, (id, volume)| {
+                acc.insert(id, volume);
+                acc
+            })
         } else {
-            Err(ReadError::NoFeatureCollection(path.display().to_string()))
+            Err(ReadError::NoFeatureCollection(source.to_string()))
         }
     }
 
+    /// Builds a `FeatureCollection` in the layout read by [`Volume::from_geojson`], sorted by id.
+    pub fn to_geojson<'a>(volumes: impl IntoIterator<Item = (&'a String, &'a Self)>) -> GeoJson {
+        GeoJson::FeatureCollection(
+            volumes
+                .into_iter()
+                .sorted_by_key(|(id, _)| *id)
+                .map(|(id, volume)| {
+                    let mut feature = Feature {
+                        id: Some(Id::String(id.clone())),
+                        geometry: Some(Geometry::from(&volume.lateral_bounds)),
+                        ..Default::default()
+                    };
+                    feature.set_property("lower_level", volume.lower_level);
+                    feature.set_property("upper_level", volume.upper_level);
+                    feature
+                })
+                .collect::<FeatureCollection>(),
+        )
+    }
+
     pub fn check_level(&self) -> Result<(), ConstraintError> {
         if self.lower_level >= self.upper_level {
             return Err(ConstraintError::LowerLevelGreater);
@@ -129,3 +174,14 @@ impl Volume {
         Ok(())
     }
 }
+
+/// Inserts `volume` as `name`, appending `-2`, `-3`, ... if the name is already taken.
+pub fn insert_unique(volumes: &mut BTreeMap<String, Volume>, name: &str, volume: Volume) {
+    let mut id = name.to_string();
+    let mut n = 1;
+    while volumes.contains_key(&id) {
+        n += 1;
+        id = format!("{name}-{n}");
+    }
+    volumes.insert(id, volume);
+}
@@ -0,0 +1,185 @@
+//! JavaScript bindings for running checks and queries in the browser, e.g. in a web-based
+//! sector editor. Build with `--target wasm32-unknown-unknown --no-default-features
+//! --features wasm` and generate the glue code with `wasm-bindgen`.
+//!
+//! Data files are passed as JSON object of FIR name to file name to contents, e.g.
+//! `{ "EDMM": { "positions.toml": "..." } }`, results are returned as JSON strings.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use geo::Point;
+use itertools::Itertools;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    diagnostic::{self, Diagnostic},
+    lint, vateud8, Config, OpenData,
+};
+
+/// A data set built from in-memory files
+#[wasm_bindgen(js_name = OpenData)]
+pub struct WasmOpenData {
+    open_data: OpenData,
+    /// File contents by path relative to the data set root, to locate diagnostics
+    files: BTreeMap<PathBuf, String>,
+}
+
+#[wasm_bindgen(js_class = OpenData)]
+impl WasmOpenData {
+    /// Parses `config.toml` contents and the data files of all FIRs
+    #[wasm_bindgen(constructor)]
+    pub fn new(config: &str, firs: &str) -> Result<WasmOpenData, JsError> {
+        let mut data = WasmOpenData {
+            open_data: OpenData {
+                firs: BTreeMap::new(),
+                config: Config::from_toml_str(config)?,
+            },
+            files: BTreeMap::from([(PathBuf::from("config.toml"), config.to_string())]),
+        };
+        let firs = serde_json::from_str::<BTreeMap<String, BTreeMap<String, String>>>(firs)?;
+        for (fir_name, files) in firs {
+            data.open_data.firs.entry(fir_name.clone()).or_default();
+            for (name, contents) in files {
+                data.set_file(&fir_name, &name, &contents)?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Replaces a data file of a FIR, e.g. after it was edited, creating the FIR if needed
+    #[wasm_bindgen(js_name = setFile)]
+    pub fn set_file(&mut self, fir: &str, name: &str, contents: &str) -> Result<(), JsError> {
+        self.open_data
+            .firs
+            .entry(fir.to_string())
+            .or_default()
+            .set_file(name, contents)
+            .map_err(|e| JsError::new(&format!("{fir}/{name}: {e}")))?;
+        self.files
+            .insert(Path::new("FIRs").join(fir).join(name), contents.to_string());
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = removeFir)]
+    pub fn remove_fir(&mut self, fir: &str) {
+        self.open_data.firs.remove(fir);
+        let folder = Path::new("FIRs").join(fir);
+        self.files.retain(|path, _| !path.starts_with(&folder));
+    }
+
+    /// Runs all checks and returns the located diagnostics as JSON array. VATEUD8 checks need
+    /// the HTML of the position list, which the page has to fetch itself.
+    #[allow(
+        clippy::needless_pass_by_value,
+        reason = "wasm-bindgen passes optional strings by value"
+    )]
+    pub fn check(&self, vateud8_html: Option<String>) -> Result<String, JsError> {
+        let vateud8 = vateud8_html.as_deref().map(vateud8::parse).transpose()?;
+        let errors = self.open_data.run_checks().err().unwrap_or_default();
+        let vateud8_errors = vateud8
+            .and_then(|vateud8| vateud8.check(&self.open_data).err())
+            .unwrap_or_default();
+        let diagnostics = errors
+            .iter()
+            .map(Diagnostic::from)
+            .chain(vateud8_errors.iter().map(Diagnostic::from))
+            .collect();
+        let mut diagnostics = lint::apply(&self.open_data.config, diagnostics, |rule, _| {
+            vateud8_html.is_some() || !rule.starts_with("vateud8-")
+        });
+        diagnostic::locate_with(&mut diagnostics, |path| self.files.get(path).cloned());
+        Ok(serde_json::to_string(&diagnostics)?)
+    }
+
+    /// Volumes at a location as JSON array of `{ fir, id }`, see [`OpenData::volumes_at`]
+    #[wasm_bindgen(js_name = volumesAt)]
+    #[must_use]
+    pub fn volumes_at(&self, lat: f64, lon: f64, level: Option<u32>) -> String {
+        json!(self
+            .open_data
+            .volumes_at(Point::new(lon, lat), level.map(u64::from))
+            .into_iter()
+            .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+            .collect_vec())
+        .to_string()
+    }
+
+    /// Sectors at a location as JSON array of `{ fir, id }`, see [`OpenData::sectors_at`]
+    #[wasm_bindgen(js_name = sectorsAt)]
+    #[must_use]
+    pub fn sectors_at(&self, lat: f64, lon: f64, level: Option<u32>) -> String {
+        json!(self
+            .open_data
+            .sectors_at(Point::new(lon, lat), level.map(u64::from))
+            .into_iter()
+            .map(|(fir, id, _)| json!({ "fir": fir, "id": id }))
+            .collect_vec())
+        .to_string()
+    }
+
+    /// Positions a callsign matches as JSON array of `{ fir, id, position }`, most specific
+    /// first
+    #[wasm_bindgen(js_name = matchCallsign)]
+    #[must_use]
+    pub fn match_callsign(&self, callsign: &str) -> String {
+        json!(self
+            .open_data
+            .match_callsign(callsign)
+            .into_iter()
+            .map(|(fir, id, position)| json!({ "fir": fir, "id": id, "position": position }))
+            .collect_vec())
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::WasmOpenData;
+
+    #[test]
+    fn test_bindings() {
+        let files = crate::tests::euroscope_fir().to_files().unwrap();
+        let firs = json!({ "EDMM": files
+            .iter()
+            .map(|(name, contents)| (name.to_string(), json!(contents)))
+            .collect::<serde_json::Map<_, _>>() });
+        let Ok(mut data) = WasmOpenData::new("[vateud8]\n[firs.EDMM]\n", &firs.to_string()) else {
+            panic!("valid data set");
+        };
+
+        let parse = |s: String| serde_json::from_str::<Value>(&s).unwrap();
+        let check = |data: &WasmOpenData| {
+            let Ok(diagnostics) = data.check(None) else {
+                panic!("checks without VATEUD8 list cannot fail");
+            };
+            parse(diagnostics)
+        };
+        assert_eq!(check(&data), json!([]));
+        assert_eq!(
+            parse(data.sectors_at(48.2, 11.8, Some(100))),
+            json!([{ "fir": "EDMM", "id": "TEST" }])
+        );
+        assert_eq!(parse(data.volumes_at(48.2, 11.8, Some(300))), json!([]));
+        assert_eq!(parse(data.match_callsign("EDMM_CTR"))[0]["id"], "MMR");
+
+        let positions = &files
+            .iter()
+            .find(|(name, _)| *name == "positions.toml")
+            .unwrap()
+            .1;
+        assert!(data.set_file("EDGG", "positions.toml", positions).is_ok());
+        let diagnostics = check(&data);
+        assert_eq!(diagnostics[0]["rule"], "duplicate-position");
+        assert_eq!(diagnostics[0]["file"], "FIRs/EDGG/positions.toml");
+        assert_eq!(diagnostics[0]["line"], 1);
+
+        data.remove_fir("EDGG");
+        assert_eq!(check(&data), json!([]));
+    }
+}
@@ -0,0 +1,70 @@
+//! Exit codes of the command line tool against a temporary data directory.
+#![cfg(feature = "cli")]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+fn run(data_dir: &Path, args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_vatsim-open-data"))
+        .arg("-C")
+        .arg(data_dir)
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .code()
+        .unwrap()
+}
+
+fn data_dir(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("vod-cli-{name}-{}", std::process::id()));
+    fs::create_dir_all(root.join("FIRs/EDGG")).unwrap();
+    fs::write(root.join("config.toml"), "[vateud8]\n[firs.EDGG]\n").unwrap();
+    fs::write(
+        root.join("FIRs/EDGG/positions.toml"),
+        "[GIN]\nfrequency = 127725000\nprefix = \"EDGG\"\nstation_type = \"CTR\"\n\
+         radio_callsign = \"Langen Radar\"\nairspace_groups = []\n",
+    )
+    .unwrap();
+    root
+}
+
+#[test]
+fn test_exit_codes() {
+    let root = data_dir("exit-codes");
+    let valid = [
+        run(&root, &["check", "--no-vateud8"]),
+        run(&root, &["export", "--format", "json"]),
+        run(&root, &["query", "callsign", "EDGG_CTR"]),
+        run(&root, &["fmt", "--check"]),
+    ];
+
+    // a duplicate frequency and prefix is a finding, not an error
+    fs::write(
+        root.join("FIRs/EDGG/positions.toml"),
+        "[GIN]\nfrequency = 127725000\nprefix = \"EDGG\"\nstation_type = \"CTR\"\n\
+         radio_callsign = \"Langen Radar\"\n\n\
+         [GIX]\nfrequency = 127725000\nprefix = \"EDGG\"\nstation_type = \"CTR\"\n\
+         radio_callsign = \"Langen Radar\"\n",
+    )
+    .unwrap();
+    let findings = run(&root, &["check", "--no-vateud8"]);
+
+    fs::write(root.join("FIRs/EDGG/sectors.toml"), "[TEST\n").unwrap();
+    let broken = [
+        run(&root, &["check", "--no-vateud8"]),
+        run(&root, &["export", "--format", "json"]),
+        run(&root, &["query", "callsign", "EDGG_CTR"]),
+        run(&root, &["fmt", "--check"]),
+    ];
+    let missing = run(&root.join("missing"), &["check", "--no-vateud8"]);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(valid, [0, 0, 0, 0]);
+    assert_eq!(findings, 1);
+    assert_eq!(broken, [1, 2, 2, 2]);
+    assert_eq!(missing, 2);
+}